@@ -0,0 +1,72 @@
+use std::time::Duration;
+
+use tokio::sync::mpsc;
+use tokio_stream::{wrappers::UnboundedReceiverStream, Stream};
+
+use crate::{Cmd, Connection, ConnectionError, Keypress, Opcode, Result};
+
+impl Connection {
+    /// Stream of every decoded command from the bus, the async counterpart of
+    /// `command_receiver`
+    ///
+    /// The stream is unbounded: libcec's callback thread never blocks on it, so commands
+    /// pile up if the stream is not polled. Dropping the stream deregisters it when the next
+    /// command arrives.
+    pub fn commands_stream(&self) -> impl Stream<Item = Cmd> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.2
+            .cmd_forwarders
+            .subscribe_with(Box::new(move |command: &Cmd| {
+                sender.send(command.clone()).is_ok()
+            }));
+        UnboundedReceiverStream::new(receiver)
+    }
+
+    /// Stream of every keypress, see `commands_stream`
+    pub fn keypresses_stream(&self) -> impl Stream<Item = Keypress> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.2
+            .key_press_forwarders
+            .subscribe_with(Box::new(move |keypress: &Keypress| {
+                sender.send(*keypress).is_ok()
+            }));
+        UnboundedReceiverStream::new(receiver)
+    }
+
+    /// Async counterpart of `transmit_and_wait`
+    ///
+    /// Only the wait for the reply is async. Transmitting still blocks until the adapter
+    /// reports the frame as sent, which is bounded by the command's `transmit_timeout`.
+    /// Needs a tokio runtime with the time driver enabled.
+    ///
+    /// # Errors
+    ///
+    /// - TransmitFailed: transmitting `command` fails
+    /// - Timeout: no reply is received within `timeout`
+    /// - Cancelled: `abort_pending` was called before the reply arrived
+    pub async fn transmit_and_wait_async(
+        &self,
+        command: Cmd,
+        expected_opcode: Opcode,
+        timeout: Duration,
+    ) -> Result<Cmd> {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let waiter = self.register_waiter(
+            &command,
+            expected_opcode,
+            // Fails if the waiter already timed out, nothing to do then
+            Box::new(move |reply| drop(sender.send(reply))),
+        );
+
+        let reply = match self.transmit(command) {
+            Ok(()) => match tokio::time::timeout(timeout, receiver.recv()).await {
+                Ok(Some(reply)) => reply,
+                Ok(None) | Err(_) => Err(ConnectionError::Timeout.into()),
+            },
+            Err(e) => Err(e),
+        };
+
+        self.unregister_waiter(&waiter);
+        reply
+    }
+}
@@ -1,46 +1,109 @@
-use std::{convert::TryInto, ffi::c_int, os::raw::c_void};
+use std::{convert::TryInto, ffi::c_int, os::raw::c_void, sync::Mutex};
 
 use cec_sys::*;
 use log::trace;
 
-use crate::Callbacks;
+use crate::{
+    Alert, Callbacks, Cmd, ConnectionError, Event, Keypress, KnownLogicalAddress, LogMsg,
+    RawKeypress, Result,
+};
+
+/// Register the callback table and user data of `callbacks` with libcec
+///
+/// Only the libcec 6 ABI (`SUPPORTED_ABI`) is supported, where `libcec_set_callbacks` takes
+/// the table before the user data.
+///
+/// # Safety
+///
+/// `connection` must be null or a live libcec handle, and `callbacks` must outlive the
+/// registration, see `Connection`.
+pub(crate) unsafe fn register_callbacks(
+    connection: libcec_connection_t,
+    callbacks: &Callbacks,
+) -> Result<()> {
+    let ret =
+        unsafe { libcec_set_callbacks(connection, callbacks.table_ptr(), callbacks.user_data()) };
+    // 1 on success, 0 when libcec rejects the callbacks, -1 for a null connection
+    if ret > 0 {
+        Ok(())
+    } else {
+        Err(ConnectionError::CallbackRegistrationFailed.into())
+    }
+}
+
+/// Call the callback in `slot`, if one is set
+///
+/// The lock is held while the callback runs, so replacing it waits for the call to return.
+/// A panicking callback aborts at the `extern "C"` boundary, so the lock is never poisoned.
+fn with_callback<F: ?Sized>(slot: &Mutex<Option<Box<F>>>, call: impl FnOnce(&mut F)) {
+    if let Some(callback) = slot.lock().unwrap().as_deref_mut() {
+        call(callback);
+    }
+}
 
 pub extern "C" fn on_key_press(callbacks: *mut c_void, keypress: *const cec_keypress) {
     trace!("on_key_press: {keypress:?}");
 
-    let callbacks: *mut Callbacks = callbacks.cast();
-    if let Some(rust_callbacks) = unsafe { callbacks.as_mut() }
-        && let Some(keypress) = unsafe { keypress.as_ref() }
-        && let Some(callback) = &mut rust_callbacks.on_key_press
-        && let Ok(keypress) = (*keypress).try_into()
-    {
-        callback(keypress);
+    let callbacks: *const Callbacks = callbacks.cast();
+    let (Some(rust_callbacks), Some(keypress)) =
+        (unsafe { callbacks.as_ref() }, unsafe { keypress.as_ref() })
+    else {
+        return;
+    };
+    with_callback(&rust_callbacks.on_raw_key_press, |callback| {
+        callback(RawKeypress::from(*keypress))
+    });
+    if let Ok(keypress) = Keypress::try_from(*keypress) {
+        rust_callbacks.key_press_forwarders.forward(&keypress);
+        with_callback(&rust_callbacks.on_event, |callback| {
+            callback(Event::KeyPress(keypress))
+        });
+        with_callback(&rust_callbacks.on_key_press, |callback| callback(keypress));
     }
 }
 
 pub extern "C" fn on_cmd_received(callback: *mut c_void, cmd: *const cec_command) {
     trace!("on_cmd_received: {cmd:?}");
 
-    let callbacks: *mut Callbacks = callback.cast();
-    if let Some(callbacks) = unsafe { callbacks.as_mut() }
+    let callbacks: *const Callbacks = callback.cast();
+    if let Some(callbacks) = unsafe { callbacks.as_ref() }
         && let Some(command) = unsafe { cmd.as_ref() }
-        && let Some(callback) = &mut callbacks.on_cmd_received
-        && let Ok(command) = (*command).try_into()
+        && let Ok(command) = Cmd::try_from(*command)
     {
-        callback(command);
+        if let Some(reclaim) = callbacks.reclaim_active_source.lock().unwrap().as_mut() {
+            reclaim.on_cmd(&command);
+        }
+        callbacks.notify_cmd_waiters(&command);
+        callbacks.cmd_forwarders.forward(&command);
+        with_callback(&callbacks.on_event, |callback| {
+            callback(Event::Command(command.clone()))
+        });
+        with_callback(&callbacks.on_cmd_received, |callback| callback(command));
     }
 }
 
 pub extern "C" fn on_log_msg(callbacks: *mut c_void, log_msg: *const cec_log_message) {
-    trace!("on_log_msg: {:?}", unsafe { *log_msg });
+    let callbacks: *const Callbacks = callbacks.cast();
+    let Some(callbacks) = (unsafe { callbacks.as_ref() }) else {
+        return;
+    };
+    // libcec calls this for every log line, skip decoding when nobody is listening
+    if callbacks.on_log_msg.lock().unwrap().is_none()
+        && callbacks.on_event.lock().unwrap().is_none()
+        && callbacks.log_msg_forwarders.is_empty()
+    {
+        return;
+    }
+    trace!("on_log_msg: {:?}", unsafe { log_msg.as_ref() });
 
-    let callbacks: *mut Callbacks = callbacks.cast();
-    if let Some(callbacks) = unsafe { callbacks.as_mut() }
-        && let Some(log_message) = unsafe { log_msg.as_ref() }
-        && let Some(callback) = &mut callbacks.on_log_msg
-        && let Ok(log_message) = (*log_message).try_into()
+    if let Some(log_message) = unsafe { log_msg.as_ref() }
+        && let Ok(log_message) = LogMsg::try_from(*log_message)
     {
-        callback(log_message);
+        callbacks.log_msg_forwarders.forward(&log_message);
+        with_callback(&callbacks.on_event, |callback| {
+            callback(Event::Log(log_message.clone()))
+        });
+        with_callback(&callbacks.on_log_msg, |callback| callback(log_message));
     }
 }
 
@@ -50,13 +113,12 @@ pub unsafe extern "C" fn on_config_changed(
 ) {
     trace!("on_config_changed: {:?}", *config);
 
-    let callbacks: *mut Callbacks = callbacks.cast();
-    if let Some(callbacks) = unsafe { callbacks.as_mut() }
+    let callbacks: *const Callbacks = callbacks.cast();
+    if let Some(callbacks) = unsafe { callbacks.as_ref() }
         && let Some(config) = unsafe { config.as_ref() }
-        && let Some(callback) = &mut callbacks.on_cfg_changed
         && let Ok(config) = (*config).try_into()
     {
-        callback(config);
+        with_callback(&callbacks.on_cfg_changed, |callback| callback(config));
     }
 }
 
@@ -67,12 +129,14 @@ pub unsafe extern "C" fn on_alert(
 ) {
     trace!("on_alert: {alert:?}, {param:?}");
 
-    let callbacks: *mut Callbacks = callbacks.cast();
-    if let Some(callbacks) = unsafe { callbacks.as_mut() }
-        && let Some(callback) = &mut callbacks.on_alert
-        && let Ok(alert) = alert.try_into()
+    let callbacks: *const Callbacks = callbacks.cast();
+    if let Some(callbacks) = unsafe { callbacks.as_ref() }
+        && let Ok(alert) = Alert::try_from(alert)
     {
-        callback(alert);
+        with_callback(&callbacks.on_event, |callback| {
+            callback(Event::Alert(alert))
+        });
+        with_callback(&callbacks.on_alert, |callback| callback(alert));
     }
 }
 
@@ -82,12 +146,13 @@ pub unsafe extern "C" fn on_menu_changed(
 ) -> c_int {
     trace!("on_menu_changed: {menu_state:?}");
 
-    let callbacks: *mut Callbacks = callbacks.cast();
-    if let Some(callbacks) = unsafe { callbacks.as_mut() }
-        && let Some(callback) = &mut callbacks.on_menu_state_changed
+    let callbacks: *const Callbacks = callbacks.cast();
+    if let Some(callbacks) = unsafe { callbacks.as_ref() }
         && let Ok(menu_state) = menu_state.try_into()
     {
-        callback(menu_state);
+        with_callback(&callbacks.on_menu_state_changed, |callback| {
+            callback(menu_state)
+        });
     }
 
     0
@@ -100,11 +165,313 @@ pub unsafe extern "C" fn on_source_activated(
 ) {
     trace!("on_source_activated: {logical_address:?}, {is_activated}");
 
-    let callbacks: *mut Callbacks = callbacks.cast();
-    if let Some(callbacks) = unsafe { callbacks.as_mut() }
-        && let Some(callback) = &mut callbacks.on_source_activated
-        && let Ok(logical_address) = logical_address.try_into()
+    let callbacks: *const Callbacks = callbacks.cast();
+    if let Some(callbacks) = unsafe { callbacks.as_ref() }
+        && let Ok(address) = KnownLogicalAddress::try_from(logical_address)
     {
-        callback(logical_address, is_activated != 0);
+        let active = is_activated != 0;
+        with_callback(&callbacks.on_event, |callback| {
+            callback(Event::SourceActivated { address, active })
+        });
+        with_callback(&callbacks.on_source_activated, |callback| {
+            callback(address, active)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        ptr,
+        sync::{mpsc, Arc, Mutex},
+        thread,
+    };
+
+    use super::*;
+    use crate::{
+        transmit_queue::TransmitQueue, Cfg, Configuration, Connection, DeviceKind, LogicalAddress,
+        Opcode,
+    };
+
+    fn cfg() -> Cfg {
+        Cfg::new("sample".into(), DeviceKind::PlaybackDevice)
+    }
+
+    /// Connection that was never opened, its `transmit_async` queue drops every command
+    fn unopened(mut cfg: Cfg) -> Connection {
+        let callbacks = cfg.take_callbacks();
+        let queue = TransmitQueue::new(Box::new(|_: &Cmd| Ok(())));
+        Connection(cfg, ptr::null_mut(), callbacks, queue, None)
+    }
+
+    #[test]
+    fn test_user_data_survives_move() {
+        let connection = unopened(cfg());
+        let (user_data, table) = (connection.2.user_data(), connection.2.table_ptr());
+
+        let moved = Box::new(connection);
+        assert_eq!(moved.2.user_data(), user_data);
+        assert_eq!(moved.2.table_ptr(), table);
+        // Registered pointers must be the ones the callbacks are dispatched through
+        assert!(ptr::eq(user_data.cast::<Callbacks>(), &*moved.2));
+
+        // Never opened, skip libcec_close/libcec_destroy
+        std::mem::forget(moved);
+    }
+
+    #[test]
+    fn test_connection_shared_between_threads() {
+        let connection = unopened(cfg());
+
+        let receivers = thread::scope(|scope| {
+            let handles: Vec<_> = (0..4)
+                .map(|_| scope.spawn(|| connection.command_receiver()))
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        let command: cec_command =
+            Cmd::standby(LogicalAddress::Tv, LogicalAddress::Playbackdevice1).into();
+        unsafe {
+            (connection.2.table.commandReceived.unwrap())(connection.2.user_data(), &command)
+        };
+        for receiver in receivers {
+            assert_eq!(receiver.try_recv().unwrap().opcode, Opcode::Standby);
+        }
+
+        // Never opened, skip libcec_close/libcec_destroy
+        std::mem::forget(connection);
+    }
+
+    #[test]
+    fn test_callback_set_after_connect() {
+        let mut connection = unopened(cfg());
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let result = {
+            let received = received.clone();
+            connection.set_command_callback(Box::new(move |command: Cmd| {
+                received.lock().unwrap().push(command.opcode)
+            }))
+        };
+        // libcec rejects the null handle, the callback is replaced regardless
+        assert_eq!(
+            result,
+            Err(ConnectionError::CallbackRegistrationFailed.into())
+        );
+
+        let command: cec_command =
+            Cmd::standby(LogicalAddress::Tv, LogicalAddress::Playbackdevice1).into();
+        unsafe {
+            (connection.2.table.commandReceived.unwrap())(connection.2.user_data(), &command)
+        };
+        assert_eq!(*received.lock().unwrap(), vec![Opcode::Standby]);
+
+        // Never opened, skip libcec_close/libcec_destroy
+        std::mem::forget(connection);
+    }
+
+    #[test]
+    fn test_abort_pending() {
+        let connection = unopened(cfg());
+        let (sender, receiver) = mpsc::channel();
+        let command = Cmd::image_view_on(LogicalAddress::Playbackdevice1, LogicalAddress::Tv);
+        connection.register_waiter(
+            &command,
+            Opcode::ReportPowerStatus,
+            Box::new(move |reply| drop(sender.send(reply))),
+        );
+
+        connection.abort_pending();
+        assert_eq!(
+            receiver.try_recv().unwrap(),
+            Err(ConnectionError::Cancelled.into())
+        );
+        assert!(connection.2.cmd_waiters.lock().unwrap().is_empty());
+
+        // Never opened, skip libcec_close/libcec_destroy
+        std::mem::forget(connection);
+    }
+
+    #[test]
+    fn test_cleared_address_configuration() {
+        let connection = unopened(cfg());
+        let configuration =
+            Configuration::try_from(connection.cleared_address_configuration()).unwrap();
+        assert_eq!(
+            LogicalAddress::from(configuration.logical_addresses.primary),
+            LogicalAddress::Unregistered
+        );
+        assert!(configuration.logical_addresses.addresses.is_empty());
+        assert_eq!(configuration.name, "sample");
+
+        // Never opened, skip libcec_close/libcec_destroy
+        std::mem::forget(connection);
+    }
+
+    #[test]
+    fn test_clear_callback() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let mut connection = {
+            let received = received.clone();
+            let mut cfg = cfg();
+            cfg.on_command_received = Some(Box::new(move |command: Cmd| {
+                received.lock().unwrap().push(command.opcode)
+            }));
+            unopened(cfg)
+        };
+        let dispatch = |connection: &Connection, command: Cmd| {
+            let command: cec_command = command.into();
+            unsafe {
+                (connection.2.table.commandReceived.unwrap())(connection.2.user_data(), &command)
+            };
+        };
+
+        dispatch(
+            &connection,
+            Cmd::standby(LogicalAddress::Tv, LogicalAddress::Playbackdevice1),
+        );
+        // libcec rejects the null handle, the callback is cleared regardless
+        assert!(connection.clear_command_callback().is_err());
+        assert!(connection.2.on_cmd_received.lock().unwrap().is_none());
+        dispatch(
+            &connection,
+            Cmd::image_view_on(LogicalAddress::Tv, LogicalAddress::Playbackdevice1),
+        );
+        assert_eq!(*received.lock().unwrap(), vec![Opcode::Standby]);
+
+        // Never opened, skip libcec_close/libcec_destroy
+        std::mem::forget(connection);
+    }
+
+    #[test]
+    fn test_log_receiver_subscribed_after_connect() {
+        let callbacks = cfg().take_callbacks();
+        let message = c"sample";
+        let log_message = cec_log_message {
+            message: message.as_ptr(),
+            level: crate::LogLevel::Notice.repr(),
+            time: 10,
+        };
+
+        // Nobody is listening, the message is dropped
+        unsafe { (callbacks.table.logMessage.unwrap())(callbacks.user_data(), &log_message) };
+
+        let receiver = callbacks.log_msg_forwarders.subscribe();
+        unsafe { (callbacks.table.logMessage.unwrap())(callbacks.user_data(), &log_message) };
+        let received = receiver.try_recv().unwrap();
+        assert_eq!(received.message, "sample");
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_register_callbacks_null_connection() {
+        let callbacks = cfg().take_callbacks();
+        assert_eq!(
+            unsafe { register_callbacks(ptr::null_mut(), &callbacks) },
+            Err(ConnectionError::CallbackRegistrationFailed.into())
+        );
+    }
+
+    #[test]
+    fn test_event_callback_coexists() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let commands = Arc::new(Mutex::new(Vec::new()));
+        let callbacks = {
+            let events = events.clone();
+            let commands = commands.clone();
+            let mut cfg = cfg();
+            cfg.on_event = Some(Box::new(move |event: Event| {
+                events.lock().unwrap().push(event)
+            }));
+            cfg.on_command_received = Some(Box::new(move |command: Cmd| {
+                commands.lock().unwrap().push(command.opcode)
+            }));
+            cfg.take_callbacks()
+        };
+
+        let command: cec_command =
+            Cmd::standby(LogicalAddress::Tv, LogicalAddress::Playbackdevice1).into();
+        unsafe { (callbacks.table.commandReceived.unwrap())(callbacks.user_data(), &command) };
+        unsafe {
+            (callbacks.table.sourceActivated.unwrap())(
+                callbacks.user_data(),
+                LogicalAddress::Tv.repr(),
+                1,
+            )
+        };
+
+        assert_eq!(*commands.lock().unwrap(), vec![Opcode::Standby]);
+        let events = events.lock().unwrap();
+        assert!(matches!(&events[0], Event::Command(command) if command.opcode == Opcode::Standby));
+        assert!(matches!(
+            events[1],
+            Event::SourceActivated { address, active: true }
+                if address == KnownLogicalAddress::new(LogicalAddress::Tv).unwrap()
+        ));
+    }
+
+    #[cfg(feature = "builder")]
+    #[test]
+    fn test_command_callback_for() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let callbacks = {
+            let received = received.clone();
+            Connection::builder()
+                .name("sample".into())
+                .kind(DeviceKind::PlaybackDevice)
+                .command_callback_for(
+                    [Opcode::ReportPowerStatus].into(),
+                    Box::new(move |command: Cmd| received.lock().unwrap().push(command.opcode)),
+                )
+                .build()
+                .unwrap()
+                .take_callbacks()
+        };
+
+        for opcode in [Opcode::Standby, Opcode::ReportPowerStatus] {
+            let command: cec_command = Cmd::try_new(
+                LogicalAddress::Tv,
+                LogicalAddress::Playbackdevice1,
+                opcode,
+                &[],
+            )
+            .unwrap()
+            .into();
+            unsafe { (callbacks.table.commandReceived.unwrap())(callbacks.user_data(), &command) };
+        }
+
+        assert_eq!(*received.lock().unwrap(), vec![Opcode::ReportPowerStatus]);
+    }
+
+    #[test]
+    fn test_connections_own_their_callback_table() {
+        let received = [
+            Arc::new(Mutex::new(Vec::new())),
+            Arc::new(Mutex::new(Vec::new())),
+        ];
+        let callbacks = received.clone().map(|received| {
+            let mut cfg = cfg();
+            cfg.on_command_received = Some(Box::new(move |command: Cmd| {
+                received.lock().unwrap().push(command.opcode)
+            }));
+            cfg.take_callbacks()
+        });
+        assert_ne!(callbacks[0].table_ptr(), callbacks[1].table_ptr());
+
+        // Dispatch through each table the way libcec does
+        let commands = [
+            Cmd::standby(LogicalAddress::Tv, LogicalAddress::Playbackdevice1),
+            Cmd::image_view_on(LogicalAddress::Tv, LogicalAddress::Playbackdevice1),
+        ];
+        for (callbacks, command) in callbacks.iter().zip(commands) {
+            let command: cec_command = command.into();
+            unsafe { (callbacks.table.commandReceived.unwrap())(callbacks.user_data(), &command) };
+        }
+
+        assert_eq!(*received[0].lock().unwrap(), vec![Opcode::Standby]);
+        assert_eq!(*received[1].lock().unwrap(), vec![Opcode::ImageViewOn]);
     }
 }
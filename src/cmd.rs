@@ -0,0 +1,1183 @@
+use std::{
+    hash::{Hash, Hasher},
+    time::Duration,
+};
+
+use arrayvec::ArrayVec;
+use cec_sys::cec_command;
+
+use crate::types::from_u8;
+use crate::{
+    AbortReason, AnalogueBroadcastType, BroadcastSystem, Cmd, DataPacket, DeviceKind,
+    LogicalAddress, MenuLanguage, Opcode, PowerStatus, RecordSourceType, Result, SystemAudioStatus,
+    TryFromCmdError, UserControlCode, OSD_NAME_MAX_LEN,
+};
+
+/// Matches libcec's `CEC_DEFAULT_TRANSMIT_TIMEOUT`
+pub(crate) const DEFAULT_TRANSMIT_TIMEOUT: Duration = Duration::from_millis(1000);
+
+impl Cmd {
+    /// Command with the default flags and transmit timeout
+    ///
+    /// Fails with `TooManyParameters` when `parameters` does not fit a `DataPacket`.
+    pub(crate) fn try_new(
+        initiator: LogicalAddress,
+        destination: LogicalAddress,
+        opcode: Opcode,
+        parameters: &[u8],
+    ) -> Result<Self> {
+        let parameters =
+            ArrayVec::try_from(parameters).map_err(|_| TryFromCmdError::TooManyParameters)?;
+        Ok(Self {
+            initiator,
+            destination,
+            ack: false,
+            eom: true,
+            opcode,
+            parameters: DataPacket(parameters),
+            opcode_set: true,
+            transmit_timeout: DEFAULT_TRANSMIT_TIMEOUT,
+        })
+    }
+
+    fn new(
+        initiator: LogicalAddress,
+        destination: LogicalAddress,
+        opcode: Opcode,
+        parameters: &[u8],
+    ) -> Self {
+        // Constructors never pass more than a handful of bytes
+        Self::try_new(initiator, destination, opcode, parameters).unwrap()
+    }
+
+    /// `ImageViewOn` command, turns the destination (usually the TV) on
+    pub fn image_view_on(initiator: LogicalAddress, destination: LogicalAddress) -> Self {
+        Self::new(initiator, destination, Opcode::ImageViewOn, &[])
+    }
+
+    /// `Standby` command, use `LogicalAddress::BROADCAST` as destination to broadcast
+    pub fn standby(initiator: LogicalAddress, destination: LogicalAddress) -> Self {
+        Self::new(initiator, destination, Opcode::Standby, &[])
+    }
+
+    /// Broadcast `ActiveSource` command announcing `physical_address` as the active source
+    pub fn active_source(initiator: LogicalAddress, physical_address: u16) -> Self {
+        Self::new(
+            initiator,
+            LogicalAddress::BROADCAST,
+            Opcode::ActiveSource,
+            &physical_address.to_be_bytes(),
+        )
+    }
+
+    /// Broadcast `RoutingChange` command, announcing that an HDMI switch changed its input
+    /// from the device at physical address `from` to the one at `to`
+    pub fn routing_change(initiator: LogicalAddress, from: u16, to: u16) -> Self {
+        let [from_hi, from_lo] = from.to_be_bytes();
+        let [to_hi, to_lo] = to.to_be_bytes();
+        Self::new(
+            initiator,
+            LogicalAddress::BROADCAST,
+            Opcode::RoutingChange,
+            &[from_hi, from_lo, to_hi, to_lo],
+        )
+    }
+
+    /// `SystemAudioModeRequest` command asking the audio system to play the audio of the
+    /// device at `physical_address`
+    pub fn system_audio_mode_request(initiator: LogicalAddress, physical_address: u16) -> Self {
+        Self::new(
+            initiator,
+            LogicalAddress::Audiosystem,
+            Opcode::SystemAudioModeRequest,
+            &physical_address.to_be_bytes(),
+        )
+    }
+
+    /// `RequestShortAudioDescriptors` command asking the audio system which of
+    /// `audio_formats` it supports
+    ///
+    /// Each format is one byte: the audio format id in bits 7-6, 0 for the CEA-861 audio
+    /// format codes, and the format code in bits 5-0, e.g. 2 for AC-3. The audio system
+    /// replies with `ReportShortAudioDescriptors`, see `as_short_audio_descriptors`.
+    ///
+    /// # Errors
+    ///
+    /// - TooManyParameters: more than 4 formats are requested
+    pub fn request_short_audio_descriptors(
+        initiator: LogicalAddress,
+        destination: LogicalAddress,
+        audio_formats: &[u8],
+    ) -> Result<Self> {
+        if audio_formats.len() > 4 {
+            return Err(TryFromCmdError::TooManyParameters.into());
+        }
+        Self::try_new(
+            initiator,
+            destination,
+            Opcode::RequestShortAudioDescriptors,
+            audio_formats,
+        )
+    }
+
+    /// `RequestArcStart` command, sent by the audio system to ask the TV (`destination`) to
+    /// start the audio return channel
+    pub fn request_arc_start(initiator: LogicalAddress, destination: LogicalAddress) -> Self {
+        Self::new(initiator, destination, Opcode::RequestArcStart, &[])
+    }
+
+    /// `RequestArcEnd` command asking `destination` to end the audio return channel, see
+    /// `request_arc_start`
+    pub fn request_arc_end(initiator: LogicalAddress, destination: LogicalAddress) -> Self {
+        Self::new(initiator, destination, Opcode::RequestArcEnd, &[])
+    }
+
+    /// `UserControlPressed` command for `key`, the raw counterpart of `send_keypress`
+    pub fn user_control_pressed(
+        initiator: LogicalAddress,
+        destination: LogicalAddress,
+        key: UserControlCode,
+    ) -> Self {
+        Self::new(
+            initiator,
+            destination,
+            Opcode::UserControlPressed,
+            &[key.repr() as u8],
+        )
+    }
+
+    /// `UserControlRelease` command, releases the key pressed with `user_control_pressed`
+    pub fn user_control_release(initiator: LogicalAddress, destination: LogicalAddress) -> Self {
+        Self::new(initiator, destination, Opcode::UserControlRelease, &[])
+    }
+
+    /// `RecordOn` command asking `destination` to record an analogue service
+    ///
+    /// `frequency` is in units of 62.5 kHz.
+    pub fn record_on_analogue(
+        initiator: LogicalAddress,
+        destination: LogicalAddress,
+        broadcast_type: AnalogueBroadcastType,
+        frequency: u16,
+        broadcast_system: BroadcastSystem,
+    ) -> Self {
+        let [frequency_hi, frequency_lo] = frequency.to_be_bytes();
+        Self::new(
+            initiator,
+            destination,
+            Opcode::RecordOn,
+            &[
+                RecordSourceType::AnalogueService.repr() as u8,
+                broadcast_type.repr() as u8,
+                frequency_hi,
+                frequency_lo,
+                broadcast_system.repr() as u8,
+            ],
+        )
+    }
+
+    /// `RecordOff` command, stops the recording on `destination`
+    pub fn record_off(initiator: LogicalAddress, destination: LogicalAddress) -> Self {
+        Self::new(initiator, destination, Opcode::RecordOff, &[])
+    }
+
+    /// `VendorCommandWithId` command carrying the 24 bit IEEE OUI `vendor` followed by a
+    /// vendor specific `payload`
+    ///
+    /// # Errors
+    ///
+    /// - InvalidVendorId: `vendor` does not fit in 24 bits
+    /// - TooManyParameters: `payload` is longer than 61 bytes
+    pub fn vendor_command_with_id(
+        initiator: LogicalAddress,
+        destination: LogicalAddress,
+        vendor: u32,
+        payload: &[u8],
+    ) -> Result<Self> {
+        if vendor > 0xFF_FFFF {
+            return Err(TryFromCmdError::InvalidVendorId(vendor).into());
+        }
+        let mut parameters = vendor.to_be_bytes()[1..].to_vec();
+        parameters.extend_from_slice(payload);
+        Self::try_new(
+            initiator,
+            destination,
+            Opcode::VendorCommandWithId,
+            &parameters,
+        )
+    }
+
+    /// `SetMenuLanguage` command announcing `language`, a 3 letter ISO 639-2 code such as
+    /// `eng`. The CEC spec broadcasts it, see `LogicalAddress::BROADCAST`.
+    ///
+    /// # Errors
+    ///
+    /// - InvalidLanguage: `language` is not 3 ASCII letters
+    pub fn set_menu_language(
+        initiator: LogicalAddress,
+        destination: LogicalAddress,
+        language: &str,
+    ) -> Result<Self> {
+        let language = language
+            .parse::<MenuLanguage>()
+            .map_err(|_| TryFromCmdError::InvalidLanguage)?;
+        Self::try_new(
+            initiator,
+            destination,
+            Opcode::SetMenuLanguage,
+            language.as_str().as_bytes(),
+        )
+    }
+
+    /// `SetOsdName` command announcing `name` as the initiator's name, e.g. in reply to
+    /// `GiveOsdName`
+    ///
+    /// # Errors
+    ///
+    /// - OsdNameTooLong: `name` is longer than `OSD_NAME_MAX_LEN` bytes
+    pub fn set_osd_name(
+        initiator: LogicalAddress,
+        destination: LogicalAddress,
+        name: &str,
+    ) -> Result<Self> {
+        if name.len() > OSD_NAME_MAX_LEN {
+            return Err(TryFromCmdError::OsdNameTooLong(name.len()).into());
+        }
+        Self::try_new(initiator, destination, Opcode::SetOsdName, name.as_bytes())
+    }
+
+    /// This command with a zero `transmit_timeout` replaced by libcec's default of 1 second
+    ///
+    /// Any other timeout is kept.
+    pub fn with_default_timeout(mut self) -> Self {
+        if self.transmit_timeout.is_zero() {
+            self.transmit_timeout = DEFAULT_TRANSMIT_TIMEOUT;
+        }
+        self
+    }
+
+    /// libcec command with an opcode byte `Opcode` may not know, e.g. a newer CEC message,
+    /// to pass to `Connection::transmit_raw`
+    ///
+    /// The result is not a `Cmd`: converting it with `TryFrom<cec_command>` fails with
+    /// `UnknownOpcode` when the crate does not know the opcode.
+    ///
+    /// # Errors
+    ///
+    /// - TooManyParameters: `parameters` does not fit a `DataPacket`
+    pub fn raw(
+        initiator: LogicalAddress,
+        destination: LogicalAddress,
+        opcode: u8,
+        parameters: &[u8],
+    ) -> Result<cec_command> {
+        // Any opcode will do, it is overwritten below
+        let mut command: cec_command =
+            Self::try_new(initiator, destination, Opcode::VendorCommand, parameters)?.into();
+        command.opcode = opcode.into();
+        Ok(command)
+    }
+
+    /// Whether `other` carries the same message: the same initiator, destination, opcode and
+    /// parameters
+    ///
+    /// `ack`, `eom` and `transmit_timeout` describe a single transmission and are ignored,
+    /// e.g. to detect a command received twice.
+    pub fn same_message(&self, other: &Cmd) -> bool {
+        self.initiator == other.initiator
+            && self.destination == other.destination
+            && self.opcode == other.opcode
+            && self.opcode_set == other.opcode_set
+            && self.parameters == other.parameters
+    }
+
+    /// Raw CEC frame of this command, the inverse of `TryFrom<&[u8]>`
+    ///
+    /// The header byte holds the initiator and destination nibbles. POLL messages
+    /// (`opcode_set == false`) consist of the header byte only.
+    pub fn to_bytes(&self) -> ArrayVec<u8, 66> {
+        let mut bytes = ArrayVec::new();
+        bytes.push(((self.initiator.repr() as u8) << 4) | (self.destination.repr() as u8 & 0xF));
+        if self.opcode_set {
+            bytes.push(self.opcode.repr() as u8);
+            // Cannot fail, at most 64 parameters follow the two bytes above
+            bytes.try_extend_from_slice(&self.parameters).unwrap();
+        }
+        bytes
+    }
+
+    /// Keycode and the remaining operand bytes of a `UserControlPressed` command
+    ///
+    /// Some keys carry an additional operand, e.g. `PlayFunction` is followed by a
+    /// `PlayMode` and `TuneFunction` by a channel identifier. The operand is empty for
+    /// plain keys.
+    ///
+    /// Returns `None` when the opcode is not `UserControlPressed` or the keycode is unknown.
+    pub fn as_user_control_pressed_with_operand(&self) -> Option<(UserControlCode, DataPacket)> {
+        if self.opcode != Opcode::UserControlPressed {
+            return None;
+        }
+        let (keycode, operand) = self.parameters.0.split_first()?;
+        let keycode = UserControlCode::try_from(*keycode).ok()?;
+        // Cannot fail, operand is shorter than the parameters
+        let operand = ArrayVec::try_from(operand).ok()?;
+        Some((keycode, DataPacket(operand)))
+    }
+
+    /// Vendor id and payload of a `VendorCommandWithId` command
+    ///
+    /// Returns `None` when the opcode is not `VendorCommandWithId` or the packet is shorter
+    /// than the 3 byte vendor id.
+    pub fn as_vendor_command_with_id(&self) -> Option<(u32, Vec<u8>)> {
+        if self.opcode != Opcode::VendorCommandWithId {
+            return None;
+        }
+        let ([a, b, c], payload) = self.parameters.0.split_first_chunk::<3>()?;
+        Some((u32::from_be_bytes([0, *a, *b, *c]), payload.to_vec()))
+    }
+
+    /// ISO 639-2 language code announced by a `SetMenuLanguage` command, e.g. `eng`
+    ///
+    /// Returns `None` when the opcode is not `SetMenuLanguage` or the packet is not exactly
+    /// three ASCII letters.
+    pub fn as_menu_language(&self) -> Option<String> {
+        if self.opcode != Opcode::SetMenuLanguage {
+            return None;
+        }
+        let language @ [_, _, _] = self.parameters.0.as_slice() else {
+            return None;
+        };
+        if !language.iter().all(u8::is_ascii_alphabetic) {
+            return None;
+        }
+        Some(language.iter().map(|&byte| char::from(byte)).collect())
+    }
+
+    /// Name announced by a `SetOsdName` command, with surrounding whitespace and NUL
+    /// padding trimmed
+    ///
+    /// Returns `None` when the opcode is not `SetOsdName` or the packet is empty. Bytes that
+    /// are not valid UTF-8 are replaced with `U+FFFD`.
+    pub fn as_osd_name(&self) -> Option<String> {
+        if self.opcode != Opcode::SetOsdName || self.parameters.is_empty() {
+            return None;
+        }
+        let name = String::from_utf8_lossy(&self.parameters);
+        Some(
+            name.trim_matches(|c: char| c.is_whitespace() || c == '\0')
+                .to_owned(),
+        )
+    }
+
+    /// Power status reported by a `ReportPowerStatus` command
+    ///
+    /// Returns `None` when the opcode is not `ReportPowerStatus`, the packet does not carry
+    /// exactly one operand or the status is unknown.
+    pub fn as_power_status(&self) -> Option<PowerStatus> {
+        if self.opcode != Opcode::ReportPowerStatus {
+            return None;
+        }
+        let [status] = self.parameters.0.as_slice() else {
+            return None;
+        };
+        from_u8(*status)
+    }
+
+    /// CEA-861 short audio descriptors carried by a `ReportShortAudioDescriptors` command
+    ///
+    /// Each 3 byte descriptor is returned in the low 24 bits, first byte most significant,
+    /// e.g. `0x09_07_07` for 2 channel LPCM at 32, 44.1 and 48 kHz with 16, 20 and 24 bits.
+    /// Returns `None` when the opcode is not `ReportShortAudioDescriptors` or the packet is
+    /// empty or not a multiple of 3 bytes long.
+    pub fn as_short_audio_descriptors(&self) -> Option<Vec<u32>> {
+        if self.opcode != Opcode::ReportShortAudioDescriptors
+            || self.parameters.is_empty()
+            || self.parameters.len() % 3 != 0
+        {
+            return None;
+        }
+        Some(
+            self.parameters
+                .chunks_exact(3)
+                .map(|sad| u32::from_be_bytes([0, sad[0], sad[1], sad[2]]))
+                .collect(),
+        )
+    }
+
+    /// System audio mode carried by a `SystemAudioModeStatus` or `SetSystemAudioMode` command
+    ///
+    /// Returns `None` for any other opcode, when the packet does not carry exactly one
+    /// operand or the status is unknown.
+    pub fn as_system_audio_mode_status(&self) -> Option<SystemAudioStatus> {
+        if !matches!(
+            self.opcode,
+            Opcode::SystemAudioModeStatus | Opcode::SetSystemAudioMode
+        ) {
+            return None;
+        }
+        let [status] = self.parameters.0.as_slice() else {
+            return None;
+        };
+        from_u8(*status)
+    }
+
+    /// Refused opcode and reason carried by a `FeatureAbort` command
+    ///
+    /// Returns `None` when the opcode is not `FeatureAbort`, the packet is not exactly two
+    /// bytes long or either byte is unknown.
+    pub fn as_feature_abort(&self) -> Option<(Opcode, AbortReason)> {
+        if self.opcode != Opcode::FeatureAbort {
+            return None;
+        }
+        let [opcode, reason] = self.parameters.0.as_slice() else {
+            return None;
+        };
+        let opcode = Opcode::try_from(*opcode).ok()?;
+        Some((opcode, from_u8(*reason)?))
+    }
+
+    /// Original and new physical address carried by a `RoutingChange` command
+    ///
+    /// Returns `None` when the opcode is not `RoutingChange` or the packet is not exactly
+    /// four bytes long.
+    pub fn as_routing_change(&self) -> Option<(u16, u16)> {
+        if self.opcode != Opcode::RoutingChange {
+            return None;
+        }
+        let [from_hi, from_lo, to_hi, to_lo] = self.parameters.0.as_slice() else {
+            return None;
+        };
+        Some((
+            u16::from_be_bytes([*from_hi, *from_lo]),
+            u16::from_be_bytes([*to_hi, *to_lo]),
+        ))
+    }
+
+    /// Physical address and device kind announced by a `ReportPhysicalAddress` command
+    ///
+    /// Returns `None` when the opcode is not `ReportPhysicalAddress`, the packet is not
+    /// exactly three bytes long or the device kind is unknown.
+    pub fn as_physical_address(&self) -> Option<(u16, DeviceKind)> {
+        if self.opcode != Opcode::ReportPhysicalAddress {
+            return None;
+        }
+        let [high, low, kind] = self.parameters.0.as_slice() else {
+            return None;
+        };
+        Some((u16::from_be_bytes([*high, *low]), from_u8(*kind)?))
+    }
+}
+
+/// Equal when `same_message` is, `ack`, `eom` and `transmit_timeout` are ignored
+impl PartialEq for Cmd {
+    fn eq(&self, other: &Self) -> bool {
+        self.same_message(other)
+    }
+}
+
+impl Eq for Cmd {}
+
+/// Hashes the fields compared by `same_message`, consistent with `PartialEq`
+impl Hash for Cmd {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.initiator.hash(state);
+        self.destination.hash(state);
+        self.opcode.hash(state);
+        self.opcode_set.hash(state);
+        self.parameters.hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PlayMode;
+
+    fn cmd(opcode: Opcode, parameters: &[u8]) -> Cmd {
+        Cmd {
+            initiator: LogicalAddress::Tv,
+            destination: LogicalAddress::Playbackdevice1,
+            ack: false,
+            eom: true,
+            opcode,
+            parameters: DataPacket(ArrayVec::try_from(parameters).unwrap()),
+            opcode_set: true,
+            transmit_timeout: Duration::from_secs(1),
+        }
+    }
+
+    mod constructors {
+        use super::*;
+
+        #[test]
+        fn test_image_view_on() {
+            let command = Cmd::image_view_on(LogicalAddress::Playbackdevice1, LogicalAddress::Tv);
+            assert_eq!(command.initiator, LogicalAddress::Playbackdevice1);
+            assert_eq!(command.destination, LogicalAddress::Tv);
+            assert_eq!(command.opcode, Opcode::ImageViewOn);
+            assert!(command.opcode_set);
+            assert!(command.parameters.0.is_empty());
+        }
+
+        #[test]
+        fn test_standby() {
+            let command = Cmd::standby(
+                LogicalAddress::Playbackdevice1,
+                LogicalAddress::Unregistered,
+            );
+            assert_eq!(command.destination, LogicalAddress::Unregistered);
+            assert_eq!(command.opcode, Opcode::Standby);
+            assert!(command.parameters.0.is_empty());
+        }
+
+        #[test]
+        fn test_broadcast_destination() {
+            let command = Cmd::standby(LogicalAddress::Playbackdevice1, LogicalAddress::BROADCAST);
+            assert!(command.destination.is_broadcast());
+            assert_eq!(command.to_bytes().as_slice(), &[0x4f, 0x36]);
+            assert_eq!(LogicalAddress::BROADCAST.repr() as u8, 0x0f);
+        }
+
+        #[test]
+        fn test_try_new_too_many_parameters() {
+            assert!(Cmd::try_new(
+                LogicalAddress::Tv,
+                LogicalAddress::Tv,
+                Opcode::VendorCommand,
+                &[0; 64]
+            )
+            .is_ok());
+            assert_eq!(
+                Cmd::try_new(
+                    LogicalAddress::Tv,
+                    LogicalAddress::Tv,
+                    Opcode::VendorCommand,
+                    &[0; 65]
+                )
+                .unwrap_err(),
+                TryFromCmdError::TooManyParameters.into()
+            );
+        }
+
+        #[test]
+        fn test_active_source() {
+            let command = Cmd::active_source(LogicalAddress::Playbackdevice1, 0x1200);
+            assert_eq!(command.destination, LogicalAddress::Unregistered);
+            assert_eq!(command.opcode, Opcode::ActiveSource);
+            assert_eq!(command.parameters.0.as_slice(), &[0x12, 0x00]);
+        }
+
+        #[test]
+        fn test_active_source_payload() {
+            let command = Cmd::active_source(LogicalAddress::Playbackdevice1, 0x2345);
+            assert_eq!(command.parameters.len(), 2);
+            assert_eq!(command.to_bytes().as_slice(), &[0x4f, 0x82, 0x23, 0x45]);
+        }
+
+        #[test]
+        fn test_request_arc() {
+            let start = Cmd::request_arc_start(LogicalAddress::Audiosystem, LogicalAddress::Tv);
+            assert_eq!(start.opcode, Opcode::RequestArcStart);
+            assert_eq!(start.to_bytes().as_slice(), &[0x50, 0xc3]);
+            let end = Cmd::request_arc_end(LogicalAddress::Audiosystem, LogicalAddress::Tv);
+            assert_eq!(end.opcode, Opcode::RequestArcEnd);
+            assert_eq!(end.to_bytes().as_slice(), &[0x50, 0xc4]);
+        }
+
+        #[test]
+        fn test_system_audio_mode_request() {
+            let command = Cmd::system_audio_mode_request(LogicalAddress::Tv, 0x2100);
+            assert_eq!(command.initiator, LogicalAddress::Tv);
+            assert_eq!(command.destination, LogicalAddress::Audiosystem);
+            assert_eq!(command.opcode, Opcode::SystemAudioModeRequest);
+            assert_eq!(command.to_bytes().as_slice(), &[0x05, 0x70, 0x21, 0x00]);
+        }
+    }
+
+    mod transmit_timeout {
+        use super::*;
+
+        #[test]
+        fn test_with_default_timeout() {
+            let mut command = cmd(Opcode::Standby, &[]);
+            command.transmit_timeout = Duration::ZERO;
+            assert_eq!(
+                command.with_default_timeout().transmit_timeout,
+                DEFAULT_TRANSMIT_TIMEOUT
+            );
+        }
+
+        #[test]
+        fn test_keeps_nonzero_timeout() {
+            let mut command = cmd(Opcode::Standby, &[]);
+            command.transmit_timeout = Duration::from_millis(250);
+            assert_eq!(
+                command.with_default_timeout().transmit_timeout,
+                Duration::from_millis(250)
+            );
+        }
+
+        #[test]
+        fn test_received_zero_timeout() {
+            let mut ffi_command: cec_sys::cec_command = cmd(Opcode::Standby, &[]).into();
+            ffi_command.transmit_timeout = 0;
+            let command = Cmd::try_from(ffi_command).unwrap();
+            assert_eq!(command.transmit_timeout, Duration::ZERO);
+            assert_eq!(
+                command.with_default_timeout().transmit_timeout,
+                DEFAULT_TRANSMIT_TIMEOUT
+            );
+        }
+    }
+
+    mod raw_opcode {
+        use super::*;
+
+        #[test]
+        fn test_unknown_opcode() {
+            // Request Current Latency, added in CEC 2.0
+            assert!(Opcode::try_from(0xa7).is_err());
+            let command = Cmd::raw(
+                LogicalAddress::Playbackdevice1,
+                LogicalAddress::Tv,
+                0xa7,
+                &[0x10, 0x00],
+            )
+            .unwrap();
+            assert_eq!(command.opcode, 0xa7_u8.into());
+            assert_eq!(command.initiator, LogicalAddress::Playbackdevice1.repr());
+            assert_eq!(command.parameters.size, 2);
+            assert_eq!(command.opcode_set, 1);
+            assert!(Cmd::try_from(command).is_err());
+        }
+
+        #[test]
+        fn test_too_many_parameters() {
+            assert!(Cmd::raw(LogicalAddress::Tv, LogicalAddress::Tv, 0xa7, &[0; 65]).is_err());
+        }
+    }
+
+    mod same_message {
+        use super::*;
+
+        #[test]
+        fn test_ignores_transmission() {
+            let command = cmd(Opcode::ReportPowerStatus, &[0x00]);
+            let mut other = command.clone();
+            other.transmit_timeout = Duration::ZERO;
+            other.ack = true;
+            other.eom = false;
+            assert!(command.same_message(&other));
+        }
+
+        #[test]
+        fn test_different_message() {
+            let command = cmd(Opcode::ReportPowerStatus, &[0x00]);
+            assert!(!command.same_message(&cmd(Opcode::ReportPowerStatus, &[0x01])));
+            assert!(!command.same_message(&cmd(Opcode::GiveDevicePowerStatus, &[0x00])));
+            let mut other = command.clone();
+            other.initiator = LogicalAddress::Audiosystem;
+            assert!(!command.same_message(&other));
+            let mut other = command.clone();
+            other.opcode_set = false;
+            assert!(!command.same_message(&other));
+        }
+    }
+
+    mod hash {
+        use std::collections::HashSet;
+
+        use super::*;
+
+        #[test]
+        fn test_set_dedup() {
+            let command = cmd(Opcode::ReportPowerStatus, &[0x00]);
+            let mut retransmitted = command.clone();
+            retransmitted.transmit_timeout = Duration::ZERO;
+            retransmitted.ack = true;
+
+            let set: HashSet<Cmd> = [
+                command.clone(),
+                retransmitted,
+                cmd(Opcode::ReportPowerStatus, &[0x01]),
+                command,
+            ]
+            .into_iter()
+            .collect();
+            assert_eq!(set.len(), 2);
+            assert!(set.contains(&cmd(Opcode::ReportPowerStatus, &[0x01])));
+        }
+    }
+
+    mod record {
+        use super::*;
+
+        #[test]
+        fn test_record_on_analogue() {
+            // 500 MHz terrestrial NTSC-M
+            let command = Cmd::record_on_analogue(
+                LogicalAddress::Tv,
+                LogicalAddress::Recordingdevice1,
+                AnalogueBroadcastType::Terrestial,
+                8000,
+                BroadcastSystem::NtscM,
+            );
+            assert_eq!(
+                command.to_bytes().as_slice(),
+                &[0x01, 0x09, 0x03, 0x02, 0x1f, 0x40, 0x03]
+            );
+        }
+
+        #[test]
+        fn test_record_off() {
+            let command = Cmd::record_off(LogicalAddress::Tv, LogicalAddress::Recordingdevice1);
+            assert_eq!(command.to_bytes().as_slice(), &[0x01, 0x0b]);
+        }
+    }
+
+    mod raw {
+        use super::*;
+
+        #[test]
+        fn test_to_bytes() {
+            let command = Cmd::active_source(LogicalAddress::Playbackdevice1, 0x1000);
+            assert_eq!(command.to_bytes().as_slice(), &[0x4f, 0x82, 0x10, 0x00]);
+        }
+
+        #[test]
+        fn test_round_trip() {
+            let command = cmd(Opcode::ReportPowerStatus, &[0x01]);
+            let parsed = Cmd::try_from(command.to_bytes().as_slice()).unwrap();
+            assert_eq!(parsed.initiator, command.initiator);
+            assert_eq!(parsed.destination, command.destination);
+            assert_eq!(parsed.opcode, command.opcode);
+            assert_eq!(parsed.parameters, command.parameters);
+        }
+
+        #[test]
+        fn test_poll() {
+            let mut command = cmd(Opcode::None, &[]);
+            command.opcode_set = false;
+            assert_eq!(command.to_bytes().as_slice(), &[0x04]);
+            let parsed = Cmd::try_from(command.to_bytes().as_slice()).unwrap();
+            assert!(!parsed.opcode_set);
+        }
+    }
+
+    mod vendor_command {
+        use super::*;
+
+        #[test]
+        fn test_packing() {
+            let command = Cmd::vendor_command_with_id(
+                LogicalAddress::Playbackdevice1,
+                LogicalAddress::Tv,
+                0x0000F0,
+                &[0x23, 0x01],
+            )
+            .unwrap();
+            assert_eq!(
+                command.to_bytes().as_slice(),
+                &[0x40, 0xa0, 0x00, 0x00, 0xf0, 0x23, 0x01]
+            );
+            assert_eq!(
+                command.as_vendor_command_with_id(),
+                Some((0x0000F0, vec![0x23, 0x01]))
+            );
+        }
+
+        #[test]
+        fn test_overflow() {
+            let vendor_command = |vendor, payload: &[u8]| {
+                Cmd::vendor_command_with_id(
+                    LogicalAddress::Playbackdevice1,
+                    LogicalAddress::Tv,
+                    vendor,
+                    payload,
+                )
+            };
+            assert!(vendor_command(0x0000F0, &[0; 61]).is_ok());
+            assert_eq!(
+                vendor_command(0x0000F0, &[0; 62]).unwrap_err(),
+                TryFromCmdError::TooManyParameters.into()
+            );
+            assert_eq!(
+                vendor_command(0x1000000, &[]).unwrap_err(),
+                TryFromCmdError::InvalidVendorId(0x1000000).into()
+            );
+        }
+
+        #[test]
+        fn test_invalid() {
+            assert_eq!(
+                cmd(Opcode::VendorCommandWithId, &[0x00, 0x00]).as_vendor_command_with_id(),
+                None
+            );
+            assert_eq!(
+                cmd(Opcode::VendorCommand, &[0x00, 0x00, 0xf0]).as_vendor_command_with_id(),
+                None
+            );
+        }
+    }
+
+    mod menu_language {
+        use super::*;
+
+        #[test]
+        fn test_set_menu_language() {
+            let command =
+                Cmd::set_menu_language(LogicalAddress::Tv, LogicalAddress::BROADCAST, "eng")
+                    .unwrap();
+            assert_eq!(
+                command.to_bytes().as_slice(),
+                &[0x0f, 0x32, b'e', b'n', b'g']
+            );
+            assert_eq!(command.as_menu_language().as_deref(), Some("eng"));
+        }
+
+        #[test]
+        fn test_invalid() {
+            for language in ["en", "engl", "e1g"] {
+                assert_eq!(
+                    Cmd::set_menu_language(LogicalAddress::Tv, LogicalAddress::BROADCAST, language)
+                        .unwrap_err(),
+                    TryFromCmdError::InvalidLanguage.into()
+                );
+            }
+            assert_eq!(cmd(Opcode::SetMenuLanguage, b"en").as_menu_language(), None);
+            assert_eq!(
+                cmd(Opcode::GetMenuLanguage, b"eng").as_menu_language(),
+                None
+            );
+        }
+    }
+
+    mod osd_name {
+        use super::*;
+
+        #[test]
+        fn test_set_osd_name() {
+            let command = Cmd::set_osd_name(
+                LogicalAddress::Playbackdevice1,
+                LogicalAddress::Tv,
+                "Living Room TV",
+            )
+            .unwrap();
+            assert_eq!(command.opcode, Opcode::SetOsdName);
+            assert_eq!(command.parameters.as_slice(), b"Living Room TV");
+            assert_eq!(command.as_osd_name().as_deref(), Some("Living Room TV"));
+        }
+
+        #[test]
+        fn test_too_long() {
+            assert_eq!(
+                Cmd::set_osd_name(
+                    LogicalAddress::Playbackdevice1,
+                    LogicalAddress::Tv,
+                    "Living Room TVs"
+                )
+                .unwrap_err(),
+                TryFromCmdError::OsdNameTooLong(15).into()
+            );
+        }
+
+        #[test]
+        fn test_as_osd_name() {
+            assert_eq!(
+                cmd(Opcode::SetOsdName, b" Kodi \0")
+                    .as_osd_name()
+                    .as_deref(),
+                Some("Kodi")
+            );
+            assert_eq!(cmd(Opcode::SetOsdName, &[]).as_osd_name(), None);
+            assert_eq!(cmd(Opcode::GiveOsdName, b"Kodi").as_osd_name(), None);
+        }
+    }
+
+    mod power_status {
+        use super::*;
+
+        #[test]
+        fn test_valid() {
+            let command = cmd(
+                Opcode::ReportPowerStatus,
+                &[PowerStatus::Standby.repr() as u8],
+            );
+            assert_eq!(command.as_power_status(), Some(PowerStatus::Standby));
+        }
+
+        #[test]
+        fn test_truncated() {
+            assert_eq!(cmd(Opcode::ReportPowerStatus, &[]).as_power_status(), None);
+        }
+
+        #[test]
+        fn test_invalid() {
+            assert_eq!(
+                cmd(
+                    Opcode::GiveDevicePowerStatus,
+                    &[PowerStatus::On.repr() as u8]
+                )
+                .as_power_status(),
+                None
+            );
+            assert_eq!(
+                cmd(Opcode::ReportPowerStatus, &[0x42]).as_power_status(),
+                None
+            );
+        }
+    }
+
+    mod short_audio_descriptors {
+        use super::*;
+
+        #[test]
+        fn test_request() {
+            // LPCM and AC-3
+            let command = Cmd::request_short_audio_descriptors(
+                LogicalAddress::Tv,
+                LogicalAddress::Audiosystem,
+                &[0x01, 0x02],
+            )
+            .unwrap();
+            assert_eq!(command.to_bytes().as_slice(), &[0x05, 0xa4, 0x01, 0x02]);
+        }
+
+        #[test]
+        fn test_request_too_many_formats() {
+            assert_eq!(
+                Cmd::request_short_audio_descriptors(
+                    LogicalAddress::Tv,
+                    LogicalAddress::Audiosystem,
+                    &[1, 2, 3, 4, 5],
+                )
+                .unwrap_err(),
+                TryFromCmdError::TooManyParameters.into()
+            );
+        }
+
+        #[test]
+        fn test_report() {
+            // 2 channel LPCM at 32/44.1/48 kHz with 16/20/24 bits, then 6 channel AC-3 at
+            // 32/44.1/48 kHz up to 640 kbit/s
+            let command = cmd(
+                Opcode::ReportShortAudioDescriptors,
+                &[0x09, 0x07, 0x07, 0x15, 0x07, 0x50],
+            );
+            assert_eq!(
+                command.as_short_audio_descriptors(),
+                Some(vec![0x09_07_07, 0x15_07_50])
+            );
+        }
+
+        #[test]
+        fn test_report_invalid() {
+            assert_eq!(
+                cmd(Opcode::ReportShortAudioDescriptors, &[]).as_short_audio_descriptors(),
+                None
+            );
+            assert_eq!(
+                cmd(Opcode::ReportShortAudioDescriptors, &[0x09, 0x07])
+                    .as_short_audio_descriptors(),
+                None
+            );
+            assert_eq!(
+                cmd(Opcode::RequestShortAudioDescriptors, &[0x09, 0x07, 0x07])
+                    .as_short_audio_descriptors(),
+                None
+            );
+        }
+    }
+
+    mod system_audio_mode {
+        use super::*;
+
+        #[test]
+        fn test_valid() {
+            let on = SystemAudioStatus::On.repr() as u8;
+            let off = SystemAudioStatus::Off.repr() as u8;
+            assert_eq!(
+                cmd(Opcode::SystemAudioModeStatus, &[on]).as_system_audio_mode_status(),
+                Some(SystemAudioStatus::On)
+            );
+            assert_eq!(
+                cmd(Opcode::SetSystemAudioMode, &[off]).as_system_audio_mode_status(),
+                Some(SystemAudioStatus::Off)
+            );
+        }
+
+        #[test]
+        fn test_invalid() {
+            assert_eq!(
+                cmd(Opcode::SystemAudioModeStatus, &[]).as_system_audio_mode_status(),
+                None
+            );
+            assert_eq!(
+                cmd(Opcode::SystemAudioModeStatus, &[0x42]).as_system_audio_mode_status(),
+                None
+            );
+            assert_eq!(
+                cmd(Opcode::GiveSystemAudioModeStatus, &[0x01]).as_system_audio_mode_status(),
+                None
+            );
+        }
+    }
+
+    mod feature_abort {
+        use super::*;
+
+        #[test]
+        fn test_valid() {
+            let command = cmd(
+                Opcode::FeatureAbort,
+                &[
+                    Opcode::GiveAudioStatus.repr() as u8,
+                    AbortReason::NotInCorrectModeToRespond.repr() as u8,
+                ],
+            );
+            assert_eq!(
+                command.as_feature_abort(),
+                Some((
+                    Opcode::GiveAudioStatus,
+                    AbortReason::NotInCorrectModeToRespond
+                ))
+            );
+        }
+
+        #[test]
+        fn test_invalid() {
+            let give_audio_status = Opcode::GiveAudioStatus.repr() as u8;
+            assert_eq!(
+                cmd(Opcode::FeatureAbort, &[give_audio_status]).as_feature_abort(),
+                None
+            );
+            assert_eq!(
+                cmd(Opcode::FeatureAbort, &[give_audio_status, 0x42]).as_feature_abort(),
+                None
+            );
+            assert_eq!(
+                cmd(Opcode::Abort, &[give_audio_status, 0x00]).as_feature_abort(),
+                None
+            );
+        }
+    }
+
+    mod routing_change {
+        use super::*;
+
+        #[test]
+        fn test_routing_change() {
+            let command = Cmd::routing_change(LogicalAddress::Tv, 0x1000, 0x2100);
+            assert!(command.destination.is_broadcast());
+            assert_eq!(
+                command.to_bytes().as_slice(),
+                &[0x0f, 0x80, 0x10, 0x00, 0x21, 0x00]
+            );
+            assert_eq!(command.as_routing_change(), Some((0x1000, 0x2100)));
+        }
+
+        #[test]
+        fn test_from_frame() {
+            let command = Cmd::try_from(&[0x0f, 0x80, 0x12, 0x34, 0x56, 0x78][..]).unwrap();
+            assert_eq!(command.as_routing_change(), Some((0x1234, 0x5678)));
+        }
+
+        #[test]
+        fn test_invalid() {
+            assert_eq!(
+                cmd(Opcode::RoutingChange, &[0x10, 0x00, 0x20]).as_routing_change(),
+                None
+            );
+            assert_eq!(
+                cmd(Opcode::RoutingInformation, &[0x10, 0x00, 0x20, 0x00]).as_routing_change(),
+                None
+            );
+        }
+    }
+
+    mod physical_address {
+        use super::*;
+
+        #[test]
+        fn test_valid() {
+            let command = cmd(Opcode::ReportPhysicalAddress, &[0x10, 0x00, 0x04]);
+            assert_eq!(
+                command.as_physical_address(),
+                Some((0x1000, DeviceKind::PlaybackDevice))
+            );
+        }
+
+        #[test]
+        fn test_invalid() {
+            assert_eq!(
+                cmd(Opcode::ReportPhysicalAddress, &[0x10, 0x00]).as_physical_address(),
+                None
+            );
+            assert_eq!(
+                cmd(Opcode::ReportPhysicalAddress, &[0x10, 0x00, 0x42]).as_physical_address(),
+                None
+            );
+            assert_eq!(
+                cmd(Opcode::ActiveSource, &[0x10, 0x00, 0x04]).as_physical_address(),
+                None
+            );
+        }
+    }
+
+    mod user_control_pressed {
+        use super::*;
+
+        #[test]
+        fn test_with_operand() {
+            let command = cmd(
+                Opcode::UserControlPressed,
+                &[
+                    UserControlCode::PlayFunction.repr() as u8,
+                    PlayMode::PlayForward.repr() as u8,
+                ],
+            );
+            let (keycode, operand) = command.as_user_control_pressed_with_operand().unwrap();
+            assert_eq!(keycode, UserControlCode::PlayFunction);
+            assert_eq!(operand.0.as_slice(), &[PlayMode::PlayForward.repr() as u8]);
+        }
+
+        #[test]
+        fn test_without_operand() {
+            let command = cmd(
+                Opcode::UserControlPressed,
+                &[UserControlCode::Select.repr() as u8],
+            );
+            let (keycode, operand) = command.as_user_control_pressed_with_operand().unwrap();
+            assert_eq!(keycode, UserControlCode::Select);
+            assert!(operand.0.is_empty());
+        }
+
+        #[test]
+        fn test_constructors() {
+            let pressed = Cmd::user_control_pressed(
+                LogicalAddress::Playbackdevice1,
+                LogicalAddress::Tv,
+                UserControlCode::Power,
+            );
+            assert_eq!(pressed.to_bytes().as_slice(), &[0x40, 0x44, 0x40]);
+            assert_eq!(
+                pressed.as_user_control_pressed_with_operand().unwrap().0,
+                UserControlCode::Power
+            );
+
+            let released =
+                Cmd::user_control_release(LogicalAddress::Playbackdevice1, LogicalAddress::Tv);
+            assert_eq!(released.to_bytes().as_slice(), &[0x40, 0x45]);
+        }
+
+        #[test]
+        fn test_invalid() {
+            assert!(cmd(Opcode::UserControlPressed, &[])
+                .as_user_control_pressed_with_operand()
+                .is_none());
+            assert!(cmd(Opcode::UserControlRelease, &[0x00])
+                .as_user_control_pressed_with_operand()
+                .is_none());
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use crate::{
+    Cmd, Connection, DeviceKind, LogicalAddress, LogicalAddresses, PowerStatus, Result,
+    UserControlCode,
+};
+
+/// Operations shared by `Connection` and the `mock` feature's `MockConnection`
+///
+/// Covers transmitting, key presses, power control, the active source and volume. Write
+/// application code against `&impl CecController` or `&dyn CecController` to inject the bus,
+/// e.g. to test it without an adapter. The inherent methods of `Connection` are unchanged,
+/// this trait only forwards to them.
+pub trait CecController {
+    fn transmit(&self, command: Cmd) -> Result<()>;
+
+    fn send_keypress(
+        &self,
+        address: LogicalAddress,
+        key: UserControlCode,
+        wait: bool,
+    ) -> Result<()>;
+
+    fn send_key_release(&self, address: LogicalAddress, wait: bool) -> Result<()>;
+
+    fn send_power_on_devices(&self, address: LogicalAddress) -> Result<()>;
+
+    fn send_standby_devices(&self, address: LogicalAddress) -> Result<()>;
+
+    fn set_active_source(&self, device_type: DeviceKind) -> Result<()>;
+
+    fn set_inactive_view(&self) -> Result<()>;
+
+    fn get_active_source(&self) -> LogicalAddress;
+
+    fn get_device_power_status(&self, address: LogicalAddress) -> PowerStatus;
+
+    fn get_logical_addresses(&self) -> Result<LogicalAddresses>;
+
+    fn volume_up(&self, send_release: bool) -> Result<()>;
+
+    fn volume_down(&self, send_release: bool) -> Result<()>;
+
+    fn mute_audio(&self, send_release: bool) -> Result<()>;
+}
+
+impl<T: CecController + ?Sized> CecController for &T {
+    fn transmit(&self, command: Cmd) -> Result<()> {
+        (**self).transmit(command)
+    }
+
+    fn send_keypress(
+        &self,
+        address: LogicalAddress,
+        key: UserControlCode,
+        wait: bool,
+    ) -> Result<()> {
+        (**self).send_keypress(address, key, wait)
+    }
+
+    fn send_key_release(&self, address: LogicalAddress, wait: bool) -> Result<()> {
+        (**self).send_key_release(address, wait)
+    }
+
+    fn send_power_on_devices(&self, address: LogicalAddress) -> Result<()> {
+        (**self).send_power_on_devices(address)
+    }
+
+    fn send_standby_devices(&self, address: LogicalAddress) -> Result<()> {
+        (**self).send_standby_devices(address)
+    }
+
+    fn set_active_source(&self, device_type: DeviceKind) -> Result<()> {
+        (**self).set_active_source(device_type)
+    }
+
+    fn set_inactive_view(&self) -> Result<()> {
+        (**self).set_inactive_view()
+    }
+
+    fn get_active_source(&self) -> LogicalAddress {
+        (**self).get_active_source()
+    }
+
+    fn get_device_power_status(&self, address: LogicalAddress) -> PowerStatus {
+        (**self).get_device_power_status(address)
+    }
+
+    fn get_logical_addresses(&self) -> Result<LogicalAddresses> {
+        (**self).get_logical_addresses()
+    }
+
+    fn volume_up(&self, send_release: bool) -> Result<()> {
+        (**self).volume_up(send_release)
+    }
+
+    fn volume_down(&self, send_release: bool) -> Result<()> {
+        (**self).volume_down(send_release)
+    }
+
+    fn mute_audio(&self, send_release: bool) -> Result<()> {
+        (**self).mute_audio(send_release)
+    }
+}
+
+impl CecController for Connection {
+    fn transmit(&self, command: Cmd) -> Result<()> {
+        Connection::transmit(self, command)
+    }
+
+    fn send_keypress(
+        &self,
+        address: LogicalAddress,
+        key: UserControlCode,
+        wait: bool,
+    ) -> Result<()> {
+        Connection::send_keypress(self, address, key, wait)
+    }
+
+    fn send_key_release(&self, address: LogicalAddress, wait: bool) -> Result<()> {
+        Connection::send_key_release(self, address, wait)
+    }
+
+    fn send_power_on_devices(&self, address: LogicalAddress) -> Result<()> {
+        Connection::send_power_on_devices(self, address)
+    }
+
+    fn send_standby_devices(&self, address: LogicalAddress) -> Result<()> {
+        Connection::send_standby_devices(self, address)
+    }
+
+    fn set_active_source(&self, device_type: DeviceKind) -> Result<()> {
+        Connection::set_active_source(self, device_type)
+    }
+
+    fn set_inactive_view(&self) -> Result<()> {
+        Connection::set_inactive_view(self)
+    }
+
+    fn get_active_source(&self) -> LogicalAddress {
+        Connection::get_active_source(self)
+    }
+
+    fn get_device_power_status(&self, address: LogicalAddress) -> PowerStatus {
+        Connection::get_device_power_status(self, address)
+    }
+
+    fn get_logical_addresses(&self) -> Result<LogicalAddresses> {
+        Connection::get_logical_addresses(self)
+    }
+
+    fn volume_up(&self, send_release: bool) -> Result<()> {
+        Connection::volume_up(self, send_release)
+    }
+
+    fn volume_down(&self, send_release: bool) -> Result<()> {
+        Connection::volume_down(self, send_release)
+    }
+
+    fn mute_audio(&self, send_release: bool) -> Result<()> {
+        Connection::mute_audio(self, send_release)
+    }
+}
@@ -1,10 +1,29 @@
 use std::mem;
 
 use arrayvec::ArrayVec;
-use num_traits::ToPrimitive;
+
+use crate::cmd::DEFAULT_TRANSMIT_TIMEOUT;
 
 pub use crate::*;
 
+impl From<u8> for AudioState {
+    fn from(status: u8) -> Self {
+        Self::from_raw(status)
+    }
+}
+
+impl From<u16> for PhysicalAddress {
+    fn from(address: u16) -> Self {
+        Self(address)
+    }
+}
+
+impl From<PhysicalAddress> for u16 {
+    fn from(address: PhysicalAddress) -> Self {
+        address.0
+    }
+}
+
 impl From<KnownLogicalAddress> for LogicalAddress {
     fn from(address: KnownLogicalAddress) -> Self {
         address.0
@@ -62,7 +81,12 @@ impl From<Cmd> for cec_command {
             opcode: command.opcode.repr(),
             parameters: command.parameters.into(),
             opcode_set: command.opcode_set.into(),
-            transmit_timeout: command.transmit_timeout.as_millis() as i32,
+            // Saturate rather than wrap to a negative timeout
+            transmit_timeout: command
+                .transmit_timeout
+                .as_millis()
+                .try_into()
+                .unwrap_or(i32::MAX),
         }
     }
 }
@@ -107,7 +131,7 @@ impl From<&Cfg> for libcec_configuration {
         cfg.strDeviceName = first_n::<{ LIBCEC_OSD_NAME_SIZE as usize }>(&config.name);
         cfg.deviceTypes = DeviceKinds::new(config.kind).into();
         if let Some(v) = config.physical_address {
-            cfg.iPhysicalAddress = v;
+            cfg.iPhysicalAddress = v.into();
         }
         if let Some(v) = config.base_device {
             cfg.baseDevice = v.repr();
@@ -134,7 +158,7 @@ impl From<&Cfg> for libcec_configuration {
             cfg.bPowerOffOnStandby = v.into();
         }
         if let Some(v) = config.language.clone() {
-            cfg.strDeviceLanguage = first_n::<3>(&v);
+            cfg.strDeviceLanguage = first_n::<3>(v.as_str());
         }
         if let Some(v) = config.monitor_only {
             cfg.bMonitorOnly = v.into();
@@ -146,16 +170,16 @@ impl From<&Cfg> for libcec_configuration {
             cfg.comboKey = v.repr();
         }
         if let Some(v) = config.combo_key_timeout {
-            cfg.iComboKeyTimeoutMs = v.as_millis().to_u32().unwrap();
+            cfg.iComboKeyTimeoutMs = duration_to_ms_u32(v);
         }
         if let Some(v) = config.button_repeat_rate {
-            cfg.iButtonRepeatRateMs = v.as_millis().to_u32().unwrap();
+            cfg.iButtonRepeatRateMs = duration_to_ms_u32(v);
         }
         if let Some(v) = config.button_release_delay {
-            cfg.iButtonReleaseDelayMs = v.as_millis().to_u32().unwrap();
+            cfg.iButtonReleaseDelayMs = duration_to_ms_u32(v);
         }
         if let Some(v) = config.double_tap_timeout {
-            cfg.iDoubleTapTimeoutMs = v.as_millis().to_u32().unwrap();
+            cfg.iDoubleTapTimeoutMs = duration_to_ms_u32(v);
         }
         if let Some(v) = config.autowake_avr {
             cfg.bAutoWakeAVR = v.into();
@@ -164,55 +188,95 @@ impl From<&Cfg> for libcec_configuration {
     }
 }
 
-impl TryFrom<libcec_configuration> for Cfg {
+impl TryFrom<cec_device_type_list> for DeviceKinds {
     type Error = Error;
 
-    fn try_from(_value: libcec_configuration) -> Result<Self> {
-        todo!()
-        // Ok(Self {
-        //     on_key_press: todo!(),
-        //     on_command_received: todo!(),
-        //     on_log_message: todo!(),
-        //     on_cfg_changed: todo!(),
-        //     on_alert: todo!(),
-        //     on_menu_state_change: todo!(),
-        //     on_source_activated: todo!(),
-        //     device: todo!(),
-        //     detect_device: todo!(),
-        //     timeout: todo!(),
-        //     name: todo!(),
-        //     kind: todo!(),
-        //     physical_address: todo!(),
-        //     base_device: todo!(),
-        //     hdmi_port: todo!(),
-        //     tv_vendor: todo!(),
-        //     wake_devices: todo!(),
-        //     power_off_devices: todo!(),
-        //     settings_from_rom: todo!(),
-        //     activate_source: todo!(),
-        //     power_off_on_standby: todo!(),
-        //     language: todo!(),
-        //     monitor_only: todo!(),
-        //     adapter_type: todo!(),
-        //     combo_key: todo!(),
-        //     combo_key_timeout: todo!(),
-        //     button_repeat_rate: todo!(),
-        //     button_release_delay: todo!(),
-        //     double_tap_timeout: todo!(),
-        //     autowake_avr: todo!(),
-        // })
+    fn try_from(device_types: cec_device_type_list) -> Result<Self> {
+        let mut kinds = ArrayVec::new();
+        // Unused slots are padded with Reserved
+        for type_id in device_types.types {
+            let kind = DeviceKind::from_repr(type_id)
+                .ok_or(TryFromConfigurationError::UnknownDeviceKind)?;
+            if kind != DeviceKind::Reserved {
+                kinds.push(kind);
+            }
+        }
+        Ok(DeviceKinds(kinds))
     }
 }
 
+impl TryFrom<libcec_configuration> for Configuration {
+    type Error = Error;
+
+    fn try_from(cfg: libcec_configuration) -> Result<Self> {
+        Ok(Self {
+            name: from_c_chars(&cfg.strDeviceName),
+            kinds: cfg.deviceTypes.try_into()?,
+            autodetect_address: cfg.bAutodetectAddress != 0,
+            physical_address: cfg.iPhysicalAddress,
+            base_device: LogicalAddress::from_repr(cfg.baseDevice)
+                .ok_or(TryFromConfigurationError::UnknownBaseDevice)?,
+            hdmi_port: cfg.iHDMIPort,
+            tv_vendor: cfg.tvVendor,
+            wake_devices: cfg.wakeDevices.try_into()?,
+            power_off_devices: cfg.powerOffDevices.try_into()?,
+            server_version: cfg.serverVersion,
+            settings_from_rom: cfg.bGetSettingsFromROM != 0,
+            activate_source: cfg.bActivateSource != 0,
+            power_off_on_standby: cfg.bPowerOffOnStandby != 0,
+            logical_addresses: cfg.logicalAddresses.try_into()?,
+            language: from_c_chars(&cfg.strDeviceLanguage),
+            monitor_only: cfg.bMonitorOnly != 0,
+            cec_version: Version::from_repr(cfg.cecVersion)
+                .ok_or(TryFromConfigurationError::UnknownCecVersion)?,
+            adapter_type: AdapterType::from_repr(cfg.adapterType)
+                .ok_or(TryFromConfigurationError::UnknownAdapterType)?,
+            combo_key: UserControlCode::from_repr(cfg.comboKey)
+                .ok_or(TryFromConfigurationError::UnknownComboKey)?,
+            combo_key_timeout: Duration::from_millis(cfg.iComboKeyTimeoutMs.into()),
+            button_repeat_rate: Duration::from_millis(cfg.iButtonRepeatRateMs.into()),
+            button_release_delay: Duration::from_millis(cfg.iButtonReleaseDelayMs.into()),
+            double_tap_timeout: Duration::from_millis(cfg.iDoubleTapTimeoutMs.into()),
+            autowake_avr: cfg.bAutoWakeAVR != 0,
+            firmware_version: cfg.iFirmwareVersion,
+            firmware_build_date: (cfg.iFirmwareBuildDate != 0).then(|| {
+                SystemTime::UNIX_EPOCH + Duration::from_secs(cfg.iFirmwareBuildDate.into())
+            }),
+        })
+    }
+}
+
+impl From<cec_adapter_descriptor> for AdapterDescriptor {
+    fn from(descriptor: cec_adapter_descriptor) -> Self {
+        Self {
+            com_name: from_c_chars(&descriptor.strComName),
+            com_path: from_c_chars(&descriptor.strComPath),
+            vendor_id: descriptor.iVendorId,
+            product_id: descriptor.iProductId,
+            firmware_version: descriptor.iFirmwareVersion,
+            physical_address: descriptor.iPhysicalAddress,
+            firmware_build_date: descriptor.iFirmwareBuildDate,
+            adapter_type: AdapterType::from_repr(descriptor.adapterType)
+                .unwrap_or(AdapterType::Unknown),
+        }
+    }
+}
+
+#[cfg(feature = "builder")]
 impl From<String> for CfgBuilderError {
     fn from(s: String) -> Self {
         Self::ValidationError(s)
     }
 }
 
+#[cfg(feature = "builder")]
 impl From<UninitializedFieldError> for CfgBuilderError {
     fn from(e: UninitializedFieldError) -> Self {
-        Self::UninitializedField(e.field_name())
+        match e.field_name() {
+            "name" => Self::MissingDeviceName,
+            "kind" => Self::MissingDeviceKind,
+            field => Self::UninitializedField(field),
+        }
     }
 }
 
@@ -226,6 +290,8 @@ impl TryFrom<KnownLogicalAddress> for RegisteredLogicalAddress {
     }
 }
 
+/// A negative `transmit_timeout` becomes zero, which is kept as is rather than replaced with
+/// a default, see `Cmd::transmit_timeout`.
 impl TryFrom<cec_command> for Cmd {
     type Error = Error;
 
@@ -254,6 +320,38 @@ impl TryFrom<cec_command> for Cmd {
     }
 }
 
+/// Parse a raw CEC frame: a header byte with the initiator and destination nibbles, then
+/// the opcode and its parameters. A lone header byte is a POLL message.
+impl TryFrom<&[u8]> for Cmd {
+    type Error = Error;
+
+    fn try_from(frame: &[u8]) -> Result<Self> {
+        let (header, rest) = frame.split_first().ok_or(TryFromFrameError::Empty)?;
+        // Nibbles are 0..=15, all of which are logical addresses
+        let initiator = LogicalAddress::try_from(c_int::from(header >> 4))?;
+        let destination = LogicalAddress::try_from(c_int::from(header & 0xF))?;
+        let (opcode, parameters, opcode_set) = match rest.split_first() {
+            Some((opcode, parameters)) => (
+                Opcode::try_from(*opcode).map_err(|_| TryFromFrameError::UnknownOpcode)?,
+                parameters,
+                true,
+            ),
+            None => (Opcode::None, rest, false),
+        };
+        let parameters = ArrayVec::try_from(parameters).map_err(|_| TryFromFrameError::TooLong)?;
+        Ok(Cmd {
+            initiator,
+            destination,
+            ack: false,
+            eom: true,
+            opcode,
+            parameters: DataPacket(parameters),
+            opcode_set,
+            transmit_timeout: DEFAULT_TRANSMIT_TIMEOUT,
+        })
+    }
+}
+
 impl TryFrom<cec_log_message> for LogMsg {
     type Error = Error;
 
@@ -278,6 +376,18 @@ impl TryFrom<cec_log_message> for LogMsg {
     }
 }
 
+impl From<LogLevel> for log::Level {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => log::Level::Error,
+            LogLevel::Warning => log::Level::Warn,
+            LogLevel::Notice | LogLevel::Traffic => log::Level::Info,
+            LogLevel::Debug => log::Level::Debug,
+            LogLevel::All => log::Level::Trace,
+        }
+    }
+}
+
 impl TryFrom<cec_logical_addresses> for LogicalAddresses {
     type Error = Error;
 
@@ -289,13 +399,14 @@ impl TryFrom<cec_logical_addresses> for LogicalAddresses {
 
         let addresses = HashSet::from_iter(addresses.addresses.into_iter().enumerate().filter_map(
             |(logical_addr, addr_mask)| {
-                let logical_addr = logical_addr as c_int;
                 // If logical address x is in use, addresses.addresses[x] != 0.
-                if addr_mask != 0 {
-                    RegisteredLogicalAddress::new(LogicalAddress::try_from(logical_addr).unwrap())
-                } else {
-                    None
+                if addr_mask == 0 {
+                    return None;
                 }
+                // Indices 0..=15 are all valid logical addresses, 15 being Unregistered which
+                // is filtered out. Anything else would be skipped rather than panic.
+                let logical_addr = LogicalAddress::try_from(logical_addr as c_int).ok()?;
+                RegisteredLogicalAddress::new(logical_addr)
             },
         ));
 
@@ -328,6 +439,15 @@ impl TryFrom<cec_keypress> for Keypress {
     }
 }
 
+impl From<cec_keypress> for RawKeypress {
+    fn from(keypress: cec_keypress) -> Self {
+        RawKeypress {
+            keycode: keypress.keycode as u32,
+            duration: Duration::from_millis(keypress.duration.into()),
+        }
+    }
+}
+
 impl TryFrom<libcec_alert> for Alert {
     type Error = Error;
 
@@ -351,11 +471,23 @@ mod tests {
     #[test]
     fn check_version() {
         assert_eq!(CEC_LIB_VERSION_MAJOR, 6);
+        assert_eq!(SUPPORTED_ABI, 6);
+        assert_eq!(libcec_version().0, 6);
     }
 
     mod utils {
         use super::*;
 
+        #[test]
+        fn test_hex_bytes() {
+            assert_eq!(HexBytes(&[]).to_string(), "");
+            assert_eq!(HexBytes(&[0x10, 0x00, 0xab]).to_string(), "10 00 ab");
+            assert_eq!(
+                HexBytes(&[0xff; 20]).to_string(),
+                format!("{} ... (20 bytes)", ["ff"; 16].join(" "))
+            );
+        }
+
         #[allow(clippy::unnecessary_cast)]
         #[test]
         fn test_first_3() {
@@ -382,6 +514,13 @@ mod tests {
                 first_n::<7>("sample")
             );
         }
+
+        #[test]
+        fn test_duration_to_ms_u32() {
+            assert_eq!(duration_to_ms_u32(Duration::from_millis(1500)), 1500);
+            assert_eq!(duration_to_ms_u32(Duration::MAX), u32::MAX);
+        }
+
         #[test]
         fn test_first_0() {
             assert_eq!([] as [::std::os::raw::c_char; 0], first_n::<0>("sample"));
@@ -405,6 +544,43 @@ mod tests {
             assert_eq!(None, KnownLogicalAddress::new(LogicalAddress::Unknown));
         }
 
+        #[test]
+        fn test_address_ordering() {
+            let mut addresses = vec![
+                LogicalAddress::Unregistered,
+                LogicalAddress::Playbackdevice1,
+                LogicalAddress::Tuner1,
+                LogicalAddress::Tv,
+                LogicalAddress::Unknown,
+                LogicalAddress::Audiosystem,
+            ];
+            addresses.sort();
+            assert_eq!(
+                addresses,
+                vec![
+                    LogicalAddress::Unknown,
+                    LogicalAddress::Tv,
+                    LogicalAddress::Tuner1,
+                    LogicalAddress::Playbackdevice1,
+                    LogicalAddress::Audiosystem,
+                    LogicalAddress::Unregistered,
+                ]
+            );
+            assert!(LogicalAddress::Tv < LogicalAddress::Recordingdevice1);
+            assert!(LogicalAddress::Playbackdevice3 > LogicalAddress::Playbackdevice2);
+        }
+
+        #[test]
+        fn test_address_predicates() {
+            assert!(LogicalAddress::Tv.is_tv());
+            assert!(LogicalAddress::Audiosystem.is_audio_system());
+            assert!(LogicalAddress::Playbackdevice2.is_playback());
+            assert!(LogicalAddress::Recordingdevice3.is_recording());
+            assert!(LogicalAddress::Tuner4.is_tuner());
+            assert!(!LogicalAddress::Freeuse.is_playback());
+            assert!(!LogicalAddress::Tv.is_tuner());
+        }
+
         #[test]
         fn test_known_and_registered_address() {
             assert_eq!(
@@ -523,6 +699,186 @@ mod tests {
                     &others,
                 )
             );
+            assert_eq!(
+                Err(TryFromLogicalAddressesError::UnregisteredPrimaryWithAddresses),
+                LogicalAddresses::try_with_primary_and_addresses(
+                    &KnownLogicalAddress::new(LogicalAddress::Unregistered).unwrap(),
+                    &others,
+                )
+            );
+        }
+
+        #[test]
+        fn test_try_from_addresses() {
+            let primary = KnownLogicalAddress::new(LogicalAddress::Playbackdevice1).unwrap();
+            let addresses = LogicalAddresses::try_from_addresses(
+                primary,
+                [LogicalAddress::Audiosystem, LogicalAddress::Tuner1],
+            )
+            .unwrap();
+            assert_eq!(addresses.primary, primary);
+            assert_eq!(
+                addresses.addresses,
+                HashSet::from_iter([
+                    RegisteredLogicalAddress(LogicalAddress::Playbackdevice1),
+                    RegisteredLogicalAddress(LogicalAddress::Audiosystem),
+                    RegisteredLogicalAddress(LogicalAddress::Tuner1),
+                ])
+            );
+
+            for invalid in [LogicalAddress::Unknown, LogicalAddress::Unregistered] {
+                assert_eq!(
+                    None,
+                    LogicalAddresses::try_from_addresses(
+                        primary,
+                        [LogicalAddress::Audiosystem, invalid]
+                    )
+                );
+            }
+        }
+
+        #[test]
+        fn test_from_ffi_every_mask() {
+            // All 2^16 masks are cheap enough to check exhaustively
+            for mask in 0..=u16::MAX {
+                let mut ffi_addresses = cec_logical_addresses {
+                    primary: LogicalAddress::Tv.repr(),
+                    addresses: [0; 16],
+                };
+                for (index, in_use) in ffi_addresses.addresses.iter_mut().enumerate() {
+                    // Any nonzero value marks the address as in use
+                    *in_use = ((mask >> index) & 1) as c_int * (index as c_int + 1);
+                }
+                let addresses = LogicalAddresses::try_from(ffi_addresses).unwrap();
+                // Unregistered (index 15) is never part of the set
+                assert_eq!(
+                    addresses.addresses.len() as u32,
+                    (mask & 0x7fff).count_ones()
+                );
+                for address in addresses.addresses {
+                    let index = LogicalAddress::from(address).repr() as u16;
+                    assert_ne!(mask & (1 << index), 0);
+                }
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod audio_state {
+        use super::*;
+
+        #[test]
+        fn test_from_status() {
+            assert_eq!(
+                AudioState::from(0x32),
+                AudioState {
+                    muted: false,
+                    volume: 50
+                }
+            );
+            assert_eq!(
+                AudioState::from(0x80 | 0x64),
+                AudioState {
+                    muted: true,
+                    volume: 100
+                }
+            );
+        }
+
+        #[test]
+        fn test_from_raw() {
+            assert_eq!(
+                AudioState::from_raw(0x80),
+                AudioState {
+                    muted: true,
+                    volume: AudioState::VOLUME_MIN
+                }
+            );
+            assert_eq!(
+                AudioState::from_raw(0x32),
+                AudioState {
+                    muted: false,
+                    volume: 50
+                }
+            );
+            assert_eq!(
+                AudioState::from_raw(0x7F),
+                AudioState {
+                    muted: false,
+                    volume: AudioState::VOLUME_UNKNOWN
+                }
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod physical_address {
+        use super::*;
+
+        #[test]
+        fn test_round_trip() {
+            let address = PhysicalAddress::from(0x1234);
+            assert_eq!(address.to_string(), "1.2.3.4");
+            assert_eq!("1.2.3.4".parse::<PhysicalAddress>(), Ok(address));
+            assert_eq!(u16::from(address), 0x1234);
+            assert_eq!(PhysicalAddress::from_nibbles(1, 2, 3, 4), Some(address));
+            assert_eq!(address.nibbles(), [1, 2, 3, 4]);
+            assert_eq!(
+                "a.0.f.0".parse::<PhysicalAddress>(),
+                Ok(PhysicalAddress(0xa0f0))
+            );
+        }
+
+        #[test]
+        fn test_invalid() {
+            assert_eq!(PhysicalAddress::from_nibbles(0x10, 0, 0, 0), None);
+            for invalid in ["", "1.0.0", "1.0.0.0.0", "10.0.0.0", "1.0.0.g", "1..0.0"] {
+                assert_eq!(
+                    invalid.parse::<PhysicalAddress>(),
+                    Err(ParsePhysicalAddressError::InvalidFormat),
+                    "{invalid}"
+                );
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod menu_language {
+        use super::*;
+
+        #[test]
+        fn test_valid() {
+            let language: MenuLanguage = "eng".parse().unwrap();
+            assert_eq!(language.as_str(), "eng");
+            assert_eq!(language.to_string(), "eng");
+            assert_eq!(MenuLanguage::try_from(String::from("eng")), Ok(language));
+        }
+
+        #[test]
+        fn test_invalid() {
+            for invalid in ["en", "engl", "e1g", "", "énn"] {
+                assert_eq!(
+                    invalid.parse::<MenuLanguage>(),
+                    Err(ParseMenuLanguageError::InvalidLanguage(invalid.into())),
+                    "{invalid}"
+                );
+            }
+        }
+
+        #[cfg(feature = "builder")]
+        #[test]
+        fn test_cfg() {
+            let builder = || {
+                Connection::builder()
+                    .name("sample".into())
+                    .kind(DeviceKind::PlaybackDevice)
+            };
+            let cfg = builder().try_language("fra").unwrap().build().unwrap();
+            let ffi_cfg: libcec_configuration = (&cfg).into();
+            assert_eq!(from_c_chars(&ffi_cfg.strDeviceLanguage), "fra");
+            for invalid in ["en", "engl"] {
+                assert!(builder().try_language(invalid).is_err(), "{invalid}");
+            }
         }
     }
 
@@ -604,6 +960,19 @@ mod tests {
             expected.data[1] = 50;
             assert_eq_ffi_packet(ffi_packet, expected);
         }
+
+        #[test]
+        fn test_iteration_order() {
+            let packet = DataPacket(ArrayVec::from_iter([3, 1, 2]));
+            assert_eq!(packet.as_ref(), &[3, 1, 2]);
+            assert_eq!(&*packet, &[3, 1, 2]);
+            assert_eq!(packet.len(), 3);
+            assert_eq!(
+                (&packet).into_iter().copied().collect::<Vec<_>>(),
+                vec![3, 1, 2]
+            );
+            assert_eq!(packet.into_iter().collect::<Vec<_>>(), vec![3, 1, 2]);
+        }
     }
 
     #[cfg(test)]
@@ -698,6 +1067,55 @@ mod tests {
                 },
             )
         }
+
+        #[test]
+        fn test_from_frame() {
+            let command = Cmd::try_from([0x4f, 0x82, 0x10, 0x00].as_slice()).unwrap();
+            assert_eq!(command.initiator, LogicalAddress::Playbackdevice1);
+            assert_eq!(command.destination, LogicalAddress::Unregistered);
+            assert_eq!(command.opcode, Opcode::ActiveSource);
+            assert!(command.opcode_set);
+            assert_eq!(command.parameters.0.as_slice(), &[0x10, 0x00]);
+
+            let poll = Cmd::try_from([0x40].as_slice()).unwrap();
+            assert_eq!(poll.destination, LogicalAddress::Tv);
+            assert!(!poll.opcode_set);
+            assert!(poll.parameters.0.is_empty());
+        }
+
+        #[test]
+        fn test_from_invalid_frame() {
+            assert_eq!(
+                Cmd::try_from([].as_slice()).unwrap_err(),
+                TryFromFrameError::Empty.into()
+            );
+            assert_eq!(
+                Cmd::try_from([0x40, 0x01].as_slice()).unwrap_err(),
+                TryFromFrameError::UnknownOpcode.into()
+            );
+            let mut frame = vec![0x40, Opcode::VendorCommand.repr() as u8];
+            frame.extend([0; 65]);
+            assert_eq!(
+                Cmd::try_from(frame.as_slice()).unwrap_err(),
+                TryFromFrameError::TooLong.into()
+            );
+        }
+
+        #[test]
+        fn test_to_ffi_timeout_saturates() {
+            let command = Cmd {
+                opcode: Opcode::Standby,
+                initiator: LogicalAddress::Playbackdevice1,
+                destination: LogicalAddress::Tv,
+                parameters: DataPacket(ArrayVec::new()),
+                transmit_timeout: Duration::from_secs(u32::MAX.into()),
+                ack: false,
+                eom: true,
+                opcode_set: true,
+            };
+            let ffi_command: cec_command = command.into();
+            assert_eq!(ffi_command.transmit_timeout, i32::MAX);
+        }
     }
 
     #[cfg(test)]
@@ -723,6 +1141,381 @@ mod tests {
         }
     }
 
+    #[cfg(test)]
+    mod configuration {
+        use super::*;
+
+        fn cfg() -> Cfg {
+            Cfg::new("sample".into(), DeviceKind::PlaybackDevice)
+        }
+
+        #[cfg(feature = "builder")]
+        #[test]
+        fn test_builder_missing_fields() {
+            assert_eq!(
+                Connection::builder()
+                    .kind(DeviceKind::PlaybackDevice)
+                    .build()
+                    .unwrap_err(),
+                CfgBuilderError::MissingDeviceName
+            );
+            assert_eq!(
+                Connection::builder()
+                    .name("sample".into())
+                    .build()
+                    .unwrap_err(),
+                CfgBuilderError::MissingDeviceKind
+            );
+            // Fails before libcec is touched
+            assert!(matches!(
+                Connection::builder()
+                    .kind(DeviceKind::PlaybackDevice)
+                    .connect(),
+                Err(Error::BuilderError(CfgBuilderError::MissingDeviceName))
+            ));
+        }
+
+        #[test]
+        fn test_huge_combo_key_timeout() {
+            let mut cfg = cfg();
+            cfg.combo_key_timeout = Some(Duration::from_secs(100 * 24 * 60 * 60));
+            let ffi_cfg: libcec_configuration = (&cfg).into();
+            assert_eq!(ffi_cfg.iComboKeyTimeoutMs, u32::MAX);
+        }
+
+        #[test]
+        fn test_from_ffi() {
+            let mut cfg = cfg();
+            cfg.physical_address = Some(PhysicalAddress(0x1000));
+            cfg.hdmi_port = Some(2);
+            cfg.combo_key = Some(UserControlCode::Stop);
+            cfg.combo_key_timeout = Some(Duration::from_millis(500));
+            let ffi_cfg: libcec_configuration = (&cfg).into();
+            let configuration = Configuration::try_from(ffi_cfg).unwrap();
+            assert_eq!(configuration.name, "sample");
+            assert_eq!(
+                configuration.kinds,
+                DeviceKinds::new(DeviceKind::PlaybackDevice)
+            );
+            assert_eq!(configuration.physical_address, 0x1000);
+            assert_eq!(configuration.hdmi_port, 2);
+            assert_eq!(configuration.combo_key, UserControlCode::Stop);
+            assert_eq!(configuration.combo_key_timeout, Duration::from_millis(500));
+            assert_eq!(configuration.firmware_version, 0);
+            assert_eq!(configuration.firmware_build_date, None);
+        }
+
+        #[cfg(feature = "builder")]
+        #[test]
+        fn test_new_matches_builder() {
+            let cfg = Cfg::new("sample".into(), DeviceKind::PlaybackDevice);
+            let built = Connection::builder()
+                .name("sample".into())
+                .kind(DeviceKind::PlaybackDevice)
+                .build()
+                .unwrap();
+            assert_eq!(cfg.timeout, built.timeout);
+            assert_eq!(cfg.applied_fields(), built.applied_fields());
+            assert_eq!(
+                Configuration::try_from(libcec_configuration::from(&cfg)).unwrap(),
+                Configuration::try_from(libcec_configuration::from(&built)).unwrap()
+            );
+        }
+
+        #[test]
+        fn test_cfg_new_with_device() {
+            let cfg = Cfg::new("sample".into(), DeviceKind::PlaybackDevice)
+                .with_device("/dev/ttyACM0".into());
+            assert_eq!(cfg.device.as_deref(), Some("/dev/ttyACM0"));
+            assert_eq!(cfg.detect_device, None);
+            let cfg = Cfg::new("sample".into(), DeviceKind::PlaybackDevice).with_detect_device();
+            assert_eq!(cfg.detect_device, Some(true));
+        }
+
+        #[test]
+        fn test_firmware_from_ffi() {
+            let cfg = cfg();
+            let mut ffi_cfg: libcec_configuration = (&cfg).into();
+            ffi_cfg.iFirmwareVersion = 12;
+            ffi_cfg.iFirmwareBuildDate = 1_500_000_000;
+            let configuration = Configuration::try_from(ffi_cfg).unwrap();
+            assert_eq!(configuration.firmware_version, 12);
+            assert_eq!(
+                configuration.firmware_build_date,
+                Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_500_000_000))
+            );
+        }
+
+        #[test]
+        fn test_applied_fields() {
+            let mut cfg = cfg();
+            cfg.hdmi_port = Some(2);
+            cfg.autowake_avr = Some(true);
+            assert_eq!(
+                cfg.applied_fields(),
+                vec!["name", "kind", "hdmi_port", "autowake_avr"]
+            );
+        }
+
+        #[test]
+        fn test_clone_config() {
+            let mut cfg = cfg().with_device("/dev/ttyACM0".into());
+            cfg.hdmi_port = Some(2);
+            cfg.language = Some("eng".parse().unwrap());
+            cfg.on_command_received = Some(Box::new(|_| {}));
+            let clone = cfg.clone_config();
+            // Debug skips the callbacks, so this compares every data field
+            assert_eq!(format!("{clone:?}"), format!("{cfg:?}"));
+            assert!(cfg.on_command_received.is_some());
+            assert!(clone.on_command_received.is_none());
+        }
+
+        #[cfg(feature = "builder")]
+        #[test]
+        fn test_disable_combo_key() {
+            let cfg = Connection::builder()
+                .name("sample".into())
+                .kind(DeviceKind::PlaybackDevice)
+                .disable_combo_key()
+                .build()
+                .unwrap();
+            assert_eq!(cfg.combo_key, Some(UserControlCode::Unknown));
+            let ffi_cfg: libcec_configuration = (&cfg).into();
+            assert_eq!(ffi_cfg.comboKey, UserControlCode::Unknown.repr());
+        }
+
+        #[cfg(feature = "builder")]
+        #[test]
+        fn test_monitor_preset() {
+            let cfg = CfgBuilder::monitor_preset("monitor".into())
+                .build()
+                .unwrap();
+            assert_eq!(cfg.monitor_only, Some(true));
+            assert_eq!(cfg.activate_source, Some(false));
+            let ffi_cfg: libcec_configuration = (&cfg).into();
+            assert_eq!(ffi_cfg.bMonitorOnly, 1);
+        }
+
+        #[test]
+        fn test_validate() {
+            let mut cfg = cfg();
+            cfg.language = Some("eng".parse().unwrap());
+            assert!(cfg.validate().is_ok());
+            assert!(matches!(
+                Cfg::new("a name that is too long".into(), DeviceKind::PlaybackDevice).validate(),
+                Err(Error::ConnectionError(ConnectionError::DeviceNameTooLong(
+                    23
+                )))
+            ));
+            let mut cfg = cfg();
+            cfg.physical_address = Some(PhysicalAddress(0x1000));
+            cfg.hdmi_port = Some(2);
+            assert!(matches!(
+                cfg.validate(),
+                Err(Error::ConnectionError(
+                    ConnectionError::ConflictingAddressConfig
+                ))
+            ));
+            let mut cfg = cfg();
+            cfg.physical_address = Some(PhysicalAddress(0));
+            cfg.hdmi_port = Some(2);
+            cfg.base_device = Some(LogicalAddress::Tv);
+            assert!(cfg.validate().is_ok());
+        }
+
+        #[test]
+        fn test_validate_name_length() {
+            let name = "a".repeat(OSD_NAME_MAX_LEN);
+            let cfg = Cfg::new(name, DeviceKind::PlaybackDevice);
+            assert!(cfg.validate().is_ok());
+            let ffi_cfg: libcec_configuration = (&cfg).into();
+            assert_eq!(ffi_cfg.strDeviceName[OSD_NAME_MAX_LEN], 0);
+
+            let name = "a".repeat(OSD_NAME_MAX_LEN + 1);
+            assert_eq!(
+                Cfg::new(name, DeviceKind::PlaybackDevice).validate(),
+                Err(ConnectionError::DeviceNameTooLong(15).into())
+            );
+        }
+
+        #[test]
+        fn test_validate_device() {
+            assert!(cfg()
+                .with_device("tcp://192.168.1.2:9526".into())
+                .validate()
+                .is_ok());
+            assert!(cfg().with_device("/dev/ttyACM0".into()).validate().is_ok());
+            for device in ["tcp://host", "tcp://:9526", "tcp://host:port"] {
+                assert!(matches!(
+                    cfg().with_device(device.into()).validate(),
+                    Err(Error::ConnectionError(ConnectionError::InvalidNetworkPort(
+                        _
+                    )))
+                ));
+            }
+            assert!(matches!(
+                cfg().with_device("/dev/tty\0ACM0".into()).validate(),
+                Err(Error::ConnectionError(ConnectionError::FfiError(_)))
+            ));
+        }
+
+        #[test]
+        fn test_validate_applied_ignores_connection_settings() {
+            let cfg = cfg()
+                .with_device("tcp://host".into())
+                .with_keepalive(Duration::ZERO, LogicalAddress::Tv);
+            assert!(cfg.validate().is_err());
+            assert!(cfg.validate_applied().is_ok());
+            assert!(matches!(
+                Cfg::new("a name that is too long".into(), DeviceKind::PlaybackDevice)
+                    .validate_applied(),
+                Err(Error::ConnectionError(ConnectionError::DeviceNameTooLong(
+                    23
+                )))
+            ));
+        }
+
+        #[test]
+        fn test_validate_keepalive() {
+            let cfg = cfg().with_keepalive(Duration::ZERO, LogicalAddress::Tv);
+            assert_eq!(
+                cfg.validate(),
+                Err(ConnectionError::ZeroKeepaliveInterval.into())
+            );
+            // Fails before libcec is touched
+            assert!(matches!(
+                cfg.connect(),
+                Err(Error::ConnectionError(
+                    ConnectionError::ZeroKeepaliveInterval
+                ))
+            ));
+            assert!(cfg()
+                .with_keepalive(Duration::from_secs(30), LogicalAddress::Tv)
+                .validate()
+                .is_ok());
+        }
+
+        #[cfg(feature = "builder")]
+        #[test]
+        fn test_network_port() {
+            let cfg = Connection::builder()
+                .name("sample".into())
+                .kind(DeviceKind::PlaybackDevice)
+                .network_port("192.168.1.2", 9526)
+                .build()
+                .unwrap();
+            assert_eq!(cfg.device.as_deref(), Some("tcp://192.168.1.2:9526"));
+            assert!(cfg.validate().is_ok());
+        }
+
+        #[test]
+        fn test_nul_in_port() {
+            let connection = cfg().with_device("bad\0port".into()).connect();
+            assert!(matches!(
+                connection,
+                Err(Error::ConnectionError(ConnectionError::FfiError(_)))
+            ));
+            assert!(matches!(
+                Cfg::device_information("bad\0port", Duration::from_secs(1)),
+                Err(Error::ConnectionError(ConnectionError::FfiError(_)))
+            ));
+        }
+
+        #[test]
+        fn test_device_kinds_capacity() {
+            let mut kinds = DeviceKinds::from_slice(&[DeviceKind::PlaybackDevice; 5]).unwrap();
+            assert_eq!(
+                kinds.push(DeviceKind::AudioSystem),
+                Err(DeviceKindsError::Full(DeviceKind::AudioSystem))
+            );
+            assert_eq!(kinds.0.len(), 5);
+            assert!(DeviceKinds::from_slice(&[DeviceKind::Tv; 6]).is_err());
+        }
+
+        #[test]
+        fn test_device_kinds_reserved() {
+            // A Reserved kind is written exactly like padding, so it would be lost
+            let ffi_reserved: cec_device_type_list =
+                DeviceKinds(ArrayVec::from_iter([DeviceKind::Reserved])).into();
+            let ffi_empty: cec_device_type_list = DeviceKinds(ArrayVec::new()).into();
+            assert_eq!(ffi_reserved.types, ffi_empty.types);
+
+            let mut kinds = DeviceKinds::new(DeviceKind::PlaybackDevice);
+            assert_eq!(
+                kinds.push(DeviceKind::Reserved),
+                Err(DeviceKindsError::Reserved)
+            );
+            assert_eq!(kinds.0.len(), 1);
+            assert_eq!(
+                DeviceKinds::from_slice(&[DeviceKind::Tv, DeviceKind::Reserved]),
+                Err(DeviceKindsError::Reserved)
+            );
+        }
+
+        #[test]
+        fn test_device_kinds_from_ffi_skips_padding() {
+            let mut devices = ArrayVec::new();
+            devices.push(DeviceKind::PlaybackDevice);
+            devices.push(DeviceKind::AudioSystem);
+            let ffi_devices: cec_device_type_list = DeviceKinds(devices.clone()).into();
+            assert_eq!(
+                DeviceKinds::try_from(ffi_devices).unwrap(),
+                DeviceKinds(devices)
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod adapter {
+        use super::*;
+
+        #[test]
+        fn test_adapter_descriptor_from_ffi() {
+            let mut descriptor: cec_adapter_descriptor = unsafe { mem::zeroed() };
+            descriptor.strComName = first_n::<1024>("/dev/ttyACM0");
+            descriptor.strComPath = first_n::<1024>("/sys/devices/usb1");
+            descriptor.iVendorId = 0x2548;
+            descriptor.iProductId = 0x1002;
+            descriptor.adapterType = AdapterType::P8External.repr();
+            let adapter = AdapterDescriptor::from(descriptor);
+            assert_eq!(adapter.com_name, "/dev/ttyACM0");
+            assert_eq!(adapter.com_path, "/sys/devices/usb1");
+            assert_eq!(adapter.vendor_id, 0x2548);
+            assert_eq!(adapter.product_id, 0x1002);
+            assert_eq!(adapter.adapter_type, AdapterType::P8External);
+        }
+
+        fn adapter(com_name: &str, adapter_type: AdapterType) -> AdapterDescriptor {
+            let mut descriptor: cec_adapter_descriptor = unsafe { mem::zeroed() };
+            descriptor.strComName = first_n::<1024>(com_name);
+            descriptor.adapterType = adapter_type.repr();
+            AdapterDescriptor::from(descriptor)
+        }
+
+        #[test]
+        fn test_select_adapter() {
+            let adapters = vec![
+                adapter("/dev/ttyACM0", AdapterType::P8External),
+                adapter("RPI", AdapterType::Rpi),
+            ];
+            let selected = Cfg::select_adapter(adapters.clone(), Some(AdapterType::Rpi)).unwrap();
+            assert_eq!(selected.com_name, "RPI");
+            let selected = Cfg::select_adapter(adapters.clone(), None).unwrap();
+            assert_eq!(selected.com_name, "/dev/ttyACM0");
+            // Falls back to the first adapter when none is of the preferred type
+            let selected = Cfg::select_adapter(adapters, Some(AdapterType::Imx)).unwrap();
+            assert_eq!(selected.com_name, "/dev/ttyACM0");
+        }
+
+        #[test]
+        fn test_select_adapter_none_found() {
+            assert_eq!(
+                Cfg::select_adapter(Vec::new(), None).unwrap_err(),
+                ConnectionError::NoAdapterFound.into()
+            );
+        }
+    }
+
     #[cfg(test)]
     mod keypress {
         use super::*;
@@ -748,5 +1541,131 @@ mod tests {
             .try_into();
             assert_eq!(keypress, Err(TryFromKeypressError::UnknownKeycode.into()));
         }
+
+        #[test]
+        fn test_raw_keypress_from_ffi_unknown_code() {
+            let keypress = RawKeypress::from(cec_keypress {
+                keycode: unsafe { std::mem::transmute::<i32, cec_user_control_code>(666) },
+                duration: 300,
+            });
+            assert_eq!(keypress.keycode, 666);
+            assert_eq!(keypress.duration, Duration::from_millis(300));
+        }
+    }
+
+    #[cfg(test)]
+    mod variants {
+        use std::fmt::Debug;
+
+        use super::*;
+        use crate::types::{from_u8, U8Variants};
+
+        #[test]
+        fn test_all_counts() {
+            assert_eq!(Opcode::all().len(), 72);
+            assert_eq!(UserControlCode::all().len(), 88);
+            assert_eq!(LogicalAddress::all().len(), 17);
+        }
+
+        #[test]
+        fn test_all_unique() {
+            let opcodes: HashSet<_> = Opcode::all().iter().collect();
+            assert_eq!(opcodes.len(), Opcode::all().len());
+            let codes: HashSet<_> = UserControlCode::all().iter().collect();
+            assert_eq!(codes.len(), UserControlCode::all().len());
+            let addresses: HashSet<_> = LogicalAddress::all().iter().collect();
+            assert_eq!(addresses.len(), LogicalAddress::all().len());
+        }
+
+        #[test]
+        fn test_all_round_trip() {
+            for opcode in Opcode::all() {
+                assert_eq!(Opcode::from_repr(opcode.repr()), Some(*opcode));
+            }
+            for code in UserControlCode::all() {
+                assert_eq!(UserControlCode::from_repr(code.repr()), Some(*code));
+            }
+            assert!(LogicalAddress::all().is_sorted());
+        }
+
+        fn assert_from_u8_round_trip<T: U8Variants + Debug + PartialEq>() {
+            for variant in T::VARIANTS {
+                assert_eq!(from_u8::<T>(variant.to_u8()), Some(*variant));
+            }
+        }
+
+        #[test]
+        fn test_from_u8() {
+            assert_from_u8_round_trip::<UserControlCode>();
+            assert_from_u8_round_trip::<Opcode>();
+            assert_from_u8_round_trip::<AbortReason>();
+            assert_from_u8_round_trip::<AnalogueBroadcastType>();
+            assert_from_u8_round_trip::<BroadcastSystem>();
+            assert_from_u8_round_trip::<DeviceKind>();
+            assert_from_u8_round_trip::<PowerStatus>();
+            assert_from_u8_round_trip::<SystemAudioStatus>();
+            assert_from_u8_round_trip::<TimerClearedStatusData>();
+            assert_eq!(from_u8::<PowerStatus>(0x42), None);
+        }
+    }
+
+    #[cfg(test)]
+    mod names {
+        use super::*;
+
+        #[test]
+        fn test_user_control_code_from_str() {
+            assert_eq!("VolumeUp".parse(), Ok(UserControlCode::VolumeUp));
+            assert_eq!("power".parse(), Ok(UserControlCode::Power));
+            assert_eq!("channel_down".parse(), Ok(UserControlCode::ChannelDown));
+            assert_eq!("voldown".parse(), Ok(UserControlCode::VolumeDown));
+            assert_eq!(
+                "warp".parse::<UserControlCode>(),
+                Err(ParseNameError::UnknownUserControlCode("warp".into()))
+            );
+        }
+
+        #[test]
+        fn test_opcode_from_str() {
+            assert_eq!("Standby".parse(), Ok(Opcode::Standby));
+            assert_eq!(
+                "give-device-power-status".parse(),
+                Ok(Opcode::GiveDevicePowerStatus)
+            );
+            assert_eq!(
+                "warp".parse::<Opcode>(),
+                Err(ParseNameError::UnknownOpcode("warp".into()))
+            );
+        }
+
+        #[test]
+        fn test_debug_round_trip() {
+            for code in UserControlCode::all() {
+                assert_eq!(format!("{code:?}").parse(), Ok(*code));
+            }
+            for opcode in Opcode::all() {
+                assert_eq!(format!("{opcode:?}").parse(), Ok(*opcode));
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod log_level {
+        use super::*;
+
+        #[test]
+        fn test_into_log_level() {
+            let levels = [
+                (LogLevel::Error, log::Level::Error),
+                (LogLevel::Warning, log::Level::Warn),
+                (LogLevel::Notice, log::Level::Info),
+                (LogLevel::Traffic, log::Level::Info),
+                (LogLevel::Debug, log::Level::Debug),
+                (LogLevel::All, log::Level::Trace),
+            ];
+            for (level, expected) in levels {
+                assert_eq!(log::Level::from(level), expected);
+            }
+        }
     }
 }
@@ -0,0 +1,144 @@
+use crate::{Connection, LogicalAddress, LogicalAddresses, PowerStatus, Result, Version};
+
+/// A device on the bus, seen through a `Connection`
+///
+/// Borrows the connection, so the handle cannot outlive it and the connection cannot be
+/// closed while a `Device` exists. Nothing is cached: every method queries libcec, which
+/// may answer from its own cache or ask the device over the bus.
+#[derive(Debug, Copy, Clone)]
+pub struct Device<'a> {
+    connection: &'a Connection,
+    address: LogicalAddress,
+}
+
+/// Everything known about a device at one point in time, see `Connection::scan_bus`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceInfo {
+    pub address: LogicalAddress,
+    pub power_status: PowerStatus,
+    /// Vendor id, 0 when unknown.
+    pub vendor_id: u32,
+    /// Name shown on screen, `None` when the device did not report it.
+    pub osd_name: Option<String>,
+    /// Physical address, 0xFFFF when unknown.
+    pub physical_address: u16,
+    pub cec_version: Version,
+}
+
+impl Connection {
+    /// Handle for the device at `address`, see `Device`
+    pub fn device(&self, address: LogicalAddress) -> Device<'_> {
+        Device {
+            connection: self,
+            address,
+        }
+    }
+
+    /// Query every active device on the bus, ordered by logical address
+    ///
+    /// Each device is queried in turn, and libcec may ask it over the bus for anything not
+    /// cached yet, so this can take a few seconds on a busy bus. A device that does not
+    /// report its name is still listed, with `osd_name` set to `None`.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the active devices cannot be listed.
+    pub fn scan_bus(&self) -> Result<Vec<DeviceInfo>> {
+        Ok(sorted_addresses(&self.get_active_devices()?)
+            .into_iter()
+            .map(|address| self.device(address).info())
+            .collect())
+    }
+}
+
+/// Addresses of `addresses`, in ascending order
+fn sorted_addresses(addresses: &LogicalAddresses) -> Vec<LogicalAddress> {
+    let mut sorted: Vec<_> = addresses
+        .addresses
+        .iter()
+        .map(|&address| LogicalAddress::from(address))
+        .collect();
+    sorted.sort();
+    sorted
+}
+
+impl Device<'_> {
+    pub fn address(&self) -> LogicalAddress {
+        self.address
+    }
+
+    /// See `Connection::get_device_power_status`
+    pub fn power_status(&self) -> PowerStatus {
+        self.connection.get_device_power_status(self.address)
+    }
+
+    /// See `Connection::get_device_vendor_id`
+    pub fn vendor_id(&self) -> u32 {
+        self.connection.get_device_vendor_id(self.address)
+    }
+
+    /// See `Connection::get_device_osd_name`
+    pub fn osd_name(&self) -> Result<String> {
+        self.connection.get_device_osd_name(self.address)
+    }
+
+    /// See `Connection::get_device_physical_address`
+    pub fn physical_address(&self) -> u16 {
+        self.connection.get_device_physical_address(self.address)
+    }
+
+    /// See `Connection::get_device_cec_version`
+    pub fn cec_version(&self) -> Version {
+        self.connection.get_device_cec_version(self.address)
+    }
+
+    /// See `Connection::is_active_device`
+    pub fn is_active(&self) -> bool {
+        self.connection.is_active_device(self.address)
+    }
+
+    /// Query everything about this device at once
+    pub fn info(&self) -> DeviceInfo {
+        DeviceInfo {
+            address: self.address,
+            power_status: self.power_status(),
+            vendor_id: self.vendor_id(),
+            osd_name: self.osd_name().ok(),
+            physical_address: self.physical_address(),
+            cec_version: self.cec_version(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cec_sys::cec_logical_addresses;
+
+    use super::*;
+
+    #[test]
+    fn test_sorted_addresses() {
+        let mut ffi_addresses = cec_logical_addresses {
+            primary: LogicalAddress::Audiosystem.repr(),
+            addresses: [0; 16],
+        };
+        for address in [5, 0, 4, 11] {
+            ffi_addresses.addresses[address] = 1;
+        }
+        let addresses = LogicalAddresses::try_from(ffi_addresses).unwrap();
+        assert_eq!(
+            sorted_addresses(&addresses),
+            [
+                LogicalAddress::Tv,
+                LogicalAddress::Playbackdevice1,
+                LogicalAddress::Audiosystem,
+                LogicalAddress::Playbackdevice3,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sorted_addresses_empty() {
+        assert!(sorted_addresses(&LogicalAddresses::default()).is_empty());
+    }
+}
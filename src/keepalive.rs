@@ -0,0 +1,114 @@
+use std::{
+    io, result,
+    sync::mpsc::{self, RecvTimeoutError},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use log::debug;
+
+use crate::{LogicalAddress, TransmitFailure};
+
+/// Periodic poll keeping the CEC link to `target` alive, see `CfgBuilder::keepalive`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Keepalive {
+    pub interval: Duration,
+    pub target: LogicalAddress,
+}
+
+/// Polls one device, called on the keepalive thread
+pub(crate) type PollFn = dyn Fn(LogicalAddress) -> result::Result<(), TransmitFailure> + Send;
+
+/// Background thread polling the keepalive target
+///
+/// The thread sleeps on a channel rather than `thread::sleep`, so shutting it down wakes it
+/// immediately instead of waiting out the interval. Dropping it joins the thread, so it must
+/// be dropped before the handle is closed.
+#[derive(derive_more::Debug)]
+pub(crate) struct KeepaliveThread {
+    #[debug(skip)]
+    worker: Option<(mpsc::Sender<()>, JoinHandle<()>)>,
+}
+
+impl KeepaliveThread {
+    pub(crate) fn spawn(keepalive: Keepalive, poll: Box<PollFn>) -> io::Result<Self> {
+        let (stop, stopped) = mpsc::channel::<()>();
+        let thread = thread::Builder::new()
+            .name("cec-keepalive".into())
+            .spawn(move || {
+                // Disconnects once the sender is dropped by `shutdown`
+                while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(keepalive.interval)
+                {
+                    if let Err(e) = poll(keepalive.target) {
+                        debug!("keepalive: {e}");
+                    }
+                }
+            })?;
+        Ok(Self {
+            worker: Some((stop, thread)),
+        })
+    }
+
+    /// Stop polling and wait for the thread, a poll in progress is finished first
+    pub(crate) fn shutdown(&mut self) {
+        if let Some((stop, thread)) = self.worker.take() {
+            drop(stop);
+            // A panic on the thread only stopped the polls, nothing left to report
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for KeepaliveThread {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Instant,
+    };
+
+    use super::*;
+
+    fn counting(polled: Arc<Mutex<Vec<LogicalAddress>>>) -> Box<PollFn> {
+        Box::new(move |target| {
+            polled.lock().unwrap().push(target);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_polls_target() {
+        let polled = Arc::new(Mutex::new(Vec::new()));
+        let keepalive = Keepalive {
+            interval: Duration::from_millis(10),
+            target: LogicalAddress::Tv,
+        };
+        let thread = KeepaliveThread::spawn(keepalive, counting(polled.clone())).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while polled.lock().unwrap().len() < 2 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(5));
+        }
+        drop(thread);
+        let polled = polled.lock().unwrap();
+        assert!(polled.len() >= 2);
+        assert!(polled.iter().all(|x| *x == LogicalAddress::Tv));
+    }
+
+    #[test]
+    fn test_shutdown_does_not_wait_for_interval() {
+        let polled = Arc::new(Mutex::new(Vec::new()));
+        let keepalive = Keepalive {
+            interval: Duration::from_secs(60 * 60),
+            target: LogicalAddress::Tv,
+        };
+        let start = Instant::now();
+        drop(KeepaliveThread::spawn(keepalive, counting(polled.clone())).unwrap());
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(polled.lock().unwrap().is_empty());
+    }
+}
@@ -1,25 +1,56 @@
 #![feature(let_chains)]
 
+#[cfg(feature = "async-tokio")]
+pub(crate) mod async_tokio;
 pub(crate) mod callback;
+pub(crate) mod cmd;
+pub(crate) mod controller;
 pub(crate) mod convert;
+pub(crate) mod device;
+pub(crate) mod keepalive;
+#[cfg(feature = "mock")]
+pub(crate) mod mock;
+pub(crate) mod pending;
+pub(crate) mod reclaim;
+pub(crate) mod timer;
+pub(crate) mod transmit_queue;
 pub(crate) mod types;
+pub(crate) mod virtual_device;
 
 use std::{
     collections::HashSet,
     convert::{TryFrom, TryInto},
-    ffi::{c_int, CStr, CString},
+    ffi::{c_int, c_void, CStr, CString},
     fmt::{self, Display},
+    mem::ManuallyDrop,
+    ops::Deref,
     pin::Pin,
-    ptr::addr_of_mut,
-    result,
-    time::Duration,
+    ptr, result, slice,
+    str::{self, FromStr},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::{Duration, Instant, SystemTime},
 };
 
 use arrayvec::ArrayVec;
 use cec_sys::*;
+#[cfg(feature = "builder")]
 use derive_builder::{Builder, UninitializedFieldError};
-
+use log::{debug, trace};
+
+pub use crate::controller::*;
+pub use crate::device::*;
+pub use crate::keepalive::Keepalive;
+use crate::keepalive::KeepaliveThread;
+#[cfg(feature = "mock")]
+pub use crate::mock::*;
+use crate::pending::Pending;
+use crate::reclaim::ActiveSourceReclaim;
+pub use crate::timer::*;
+pub use crate::transmit_queue::*;
+use crate::transmit_queue::{Handle, TransmitQueue};
 pub use crate::types::*;
+pub use crate::virtual_device::*;
 
 pub type Result<T> = result::Result<T, Error>;
 
@@ -27,6 +58,8 @@ pub type Result<T> = result::Result<T, Error>;
 pub enum Error {
     #[error("failed to convert cmd: {0}")]
     TryFromCmdError(#[from] TryFromCmdError),
+    #[error("failed to parse frame: {0}")]
+    TryFromFrameError(#[from] TryFromFrameError),
     #[error("failed to convert log msg: {0}")]
     TryFromLogMsgError(#[from] TryFromLogMsgError),
     #[error("failed to convert logical address: {0}")]
@@ -37,12 +70,23 @@ pub enum Error {
     TryFromAlertError(#[from] TryFromAlertError),
     #[error("failed to convert menu state: {0}")]
     TryFromMenuStateError(#[from] TryFromMenuStateError),
+    #[error("failed to convert configuration: {0}")]
+    TryFromConfigurationError(#[from] TryFromConfigurationError),
+    #[error("invalid timer: {0}")]
+    TimerError(#[from] TimerError),
     #[error("failed to connect: {0}")]
     ConnectionError(#[from] ConnectionError),
+    #[cfg(feature = "builder")]
     #[error("builder error: {0}")]
     BuilderError(#[from] CfgBuilderError),
     #[error("nul byte found: {0}")]
     NulError(#[from] std::ffi::NulError),
+    #[error("failed to parse physical address: {0}")]
+    ParsePhysicalAddressError(#[from] ParsePhysicalAddressError),
+    #[error("failed to parse menu language: {0}")]
+    ParseMenuLanguageError(#[from] ParseMenuLanguageError),
+    #[error("failed to parse name: {0}")]
+    ParseNameError(#[from] ParseNameError),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
@@ -55,14 +99,53 @@ pub enum ConnectionError {
     AdapterOpenFailed,
     #[error("callback registration failed")]
     CallbackRegistrationFailed,
-    #[error("transmit failed")]
-    TransmitFailed,
+    #[error("transmit failed: {0}")]
+    TransmitFailed(TransmitFailure),
     #[error("device missing")]
     DeviceMissing,
+    #[error("configuration failed")]
+    ConfigurationFailed,
+    #[error("adapter cannot persist configuration")]
+    PersistNotSupported,
+    #[error("library info unavailable")]
+    LibInfoUnavailable,
+    #[error("device information unavailable")]
+    DeviceInformationUnavailable,
+    #[error("hdmi port {0} is outside of 1..=15")]
+    InvalidHdmiPort(u8),
+    #[error("primary logical address is unregistered")]
+    PrimaryAddressUnregistered,
+    #[error("timed out")]
+    Timeout,
+    #[error("cancelled")]
+    Cancelled,
+    #[error("device name is {0} bytes long, at most {OSD_NAME_MAX_LEN} are allowed")]
+    DeviceNameTooLong(usize),
+    #[error("hdmi port and base device are ignored when a physical address is set")]
+    ConflictingAddressConfig,
+    #[error("port {0:?} is not a valid tcp://host:port address")]
+    InvalidNetworkPort(String),
+    #[error("keepalive interval must not be zero")]
+    ZeroKeepaliveInterval,
+    #[error("failed to spawn thread: {0}")]
+    ThreadSpawnFailed(String),
     #[error("ffi error: {0}")]
     FfiError(#[from] std::ffi::NulError),
 }
 
+/// Context of a failed libcec call
+///
+/// libcec only reports success or failure, the adapter's command status is not exposed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransmitFailure {
+    /// Name of the libcec function that failed.
+    pub call: &'static str,
+    /// Opcode of the transmitted command, if any.
+    pub opcode: Option<Opcode>,
+    /// Logical address the call was targeting, if any.
+    pub address: Option<LogicalAddress>,
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, thiserror::Error)]
 pub enum TryFromCmdError {
     #[error("unknown opcode")]
@@ -71,6 +154,24 @@ pub enum TryFromCmdError {
     UnknownInitiator,
     #[error("unknown destination")]
     UnknownDestination,
+    #[error("too many parameters")]
+    TooManyParameters,
+    #[error("vendor id {0:#x} does not fit in 24 bits")]
+    InvalidVendorId(u32),
+    #[error("language is not a 3 character ISO 639-2 code")]
+    InvalidLanguage,
+    #[error("osd name is {0} bytes long, at most {OSD_NAME_MAX_LEN} are allowed")]
+    OsdNameTooLong(usize),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TryFromFrameError {
+    #[error("empty frame")]
+    Empty,
+    #[error("frame longer than header, opcode and 64 parameters")]
+    TooLong,
+    #[error("unknown opcode")]
+    UnknownOpcode,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, thiserror::Error)]
@@ -91,6 +192,16 @@ pub enum TryFromLogicalAddressesError {
     UnknownPrimaryAddress,
     #[error("invalid primary address")]
     InvalidPrimaryAddress,
+    #[error("primary address is unregistered but other addresses are set")]
+    UnregisteredPrimaryWithAddresses,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum DeviceKindsError {
+    #[error("{0:?} does not fit, at most 5 device kinds are supported")]
+    Full(DeviceKind),
+    #[error("Reserved marks unused slots and is not a device kind")]
+    Reserved,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, thiserror::Error)]
@@ -99,6 +210,26 @@ pub enum TryFromKeypressError {
     UnknownKeycode,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ParsePhysicalAddressError {
+    #[error("expected four hex digits separated by dots, e.g. 1.0.0.0")]
+    InvalidFormat,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ParseMenuLanguageError {
+    #[error("{0:?} is not a 3 letter ISO 639-2 code")]
+    InvalidLanguage(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ParseNameError {
+    #[error("unknown user control code {0:?}")]
+    UnknownUserControlCode(String),
+    #[error("unknown opcode {0:?}")]
+    UnknownOpcode(String),
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, thiserror::Error)]
 pub enum TryFromAlertError {
     #[error("unknown alert")]
@@ -111,9 +242,55 @@ pub enum TryFromMenuStateError {
     UnknownMenuState,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TryFromConfigurationError {
+    #[error("unknown device kind")]
+    UnknownDeviceKind,
+    #[error("unknown base device")]
+    UnknownBaseDevice,
+    #[error("unknown cec version")]
+    UnknownCecVersion,
+    #[error("unknown adapter type")]
+    UnknownAdapterType,
+    #[error("unknown combo key")]
+    UnknownComboKey,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TimerError {
+    #[error("not a timer opcode")]
+    UnexpectedOpcode,
+    #[error("invalid parameter length")]
+    InvalidLength,
+    #[error("invalid date")]
+    InvalidDate,
+    #[error("invalid time")]
+    InvalidTime,
+    #[error("invalid duration")]
+    InvalidDuration,
+    #[error("invalid recording sequence")]
+    InvalidRecordingSequence,
+    #[error("unknown analogue broadcast type")]
+    UnknownAnalogueBroadcastType,
+    #[error("unknown broadcast system")]
+    UnknownBroadcastSystem,
+    #[error("unknown external source specifier")]
+    UnknownExternalSourceSpecifier,
+}
+
+/// Why `CfgBuilder` could not build a configuration, returned by `CfgBuilder::connect` as
+/// `Error::BuilderError`
+#[cfg(feature = "builder")]
 #[derive(Debug, Eq, PartialEq, thiserror::Error)]
 #[non_exhaustive]
 pub enum CfgBuilderError {
+    /// `name` was not set
+    #[error("device name is not set")]
+    MissingDeviceName,
+    /// `kind` was not set
+    #[error("device kind is not set")]
+    MissingDeviceKind,
+    /// Another required field was not set
     #[error("uninitialized field: {0}")]
     UninitializedField(&'static str),
     #[error("validation error: {0}")]
@@ -131,9 +308,26 @@ pub struct RegisteredLogicalAddress(LogicalAddress);
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct UnregisteredLogicalAddress {}
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct DataPacket(pub ArrayVec<u8, 64>);
 
+/// HDMI physical address, one nibble per level of the HDMI topology, e.g. `1.0.0.0` for
+/// the device on the TV's first input
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct PhysicalAddress(pub u16);
+
+/// Longest OSD name the CEC spec allows in a `SetOsdName` command
+pub const OSD_NAME_MAX_LEN: usize = 14;
+
+/// Menu language, a 3 letter ISO 639-2 code such as `eng`
+///
+/// Parse it from a string, which must be exactly three ASCII letters.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MenuLanguage([u8; 3]);
+
+/// A CEC command
+///
+/// Equality and hashing only consider the message, see `same_message`.
 #[derive(Debug, Clone)]
 pub struct Cmd {
     /// The logical address of the initiator of this message.
@@ -150,7 +344,11 @@ pub struct Cmd {
     pub parameters: DataPacket,
     /// 1 when an opcode is set, 0 otherwise (POLL message).
     pub opcode_set: bool,
-    /// The timeout to use in ms.
+    /// The timeout to use in ms. Timeouts beyond `i32::MAX` ms (about 24.8 days) are
+    /// clamped when passed to libcec.
+    ///
+    /// Zero is passed to libcec as is, it does not select a default. Commands received from
+    /// libcec usually carry zero, use `with_default_timeout` before transmitting them again.
     pub transmit_timeout: Duration,
 }
 
@@ -179,42 +377,123 @@ pub struct Keypress {
     pub duration: Duration,
 }
 
+/// Keypress with the keycode as received, including codes `UserControlCode` doesn't know,
+/// e.g. vendor specific ones
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RawKeypress {
+    /// The raw keycode.
+    pub keycode: u32,
+    /// The duration of the keypress.
+    pub duration: Duration,
+}
+
+/// Audio status reported by the audio system, decoded from libcec's status byte
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct AudioState {
+    /// Whether the audio is muted.
+    pub muted: bool,
+    /// Volume between `VOLUME_MIN` and `VOLUME_MAX`, `VOLUME_UNKNOWN` when unknown.
+    pub volume: u8,
+}
+
+/// Device kinds of a libcec client, at most 5
+///
+/// libcec pads the unused slots of its list with `DeviceKind::Reserved`, so `Reserved` must
+/// not be used as a kind: it would be indistinguishable from an empty slot. `push` and
+/// `from_slice` reject it.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DeviceKinds(pub ArrayVec<DeviceKind, 5>);
 
+/// Everything delivered to the `on_event` callback
+#[derive(Debug, Clone)]
+pub enum Event {
+    KeyPress(Keypress),
+    Command(Cmd),
+    Log(LogMsg),
+    Alert(Alert),
+    SourceActivated {
+        address: KnownLogicalAddress,
+        active: bool,
+    },
+}
+
 #[derive(derive_more::Debug)]
 pub struct Callbacks {
     #[debug(skip)]
-    pub on_key_press: Option<Box<OnKeyPress>>,
+    pub on_key_press: Mutex<Option<Box<OnKeyPress>>>,
+
+    #[debug(skip)]
+    pub on_raw_key_press: Mutex<Option<Box<OnRawKeyPress>>>,
+
+    #[debug(skip)]
+    pub on_cmd_received: Mutex<Option<Box<OnCmd>>>,
 
     #[debug(skip)]
-    pub on_cmd_received: Option<Box<OnCmd>>,
+    pub on_log_msg: Mutex<Option<Box<OnLogMsg>>>,
 
     #[debug(skip)]
-    pub on_log_msg: Option<Box<OnLogMsg>>,
+    pub on_cfg_changed: Mutex<Option<Box<OnCfgChanged>>>,
 
     #[debug(skip)]
-    pub on_cfg_changed: Option<Box<OnCfgChanged>>,
+    pub on_alert: Mutex<Option<Box<OnAlert>>>,
 
     #[debug(skip)]
-    pub on_alert: Option<Box<OnAlert>>,
+    pub on_menu_state_changed: Mutex<Option<Box<OnMenuStateChanged>>>,
 
     #[debug(skip)]
-    pub on_menu_state_changed: Option<Box<OnMenuStateChanged>>,
+    pub on_source_activated: Mutex<Option<Box<OnSourceActivated>>>,
 
     #[debug(skip)]
-    pub on_source_activated: Option<Box<OnSourceActivated>>,
+    pub on_event: Mutex<Option<Box<OnEvent>>>,
+
+    pub(crate) reclaim_active_source: Mutex<Option<ActiveSourceReclaim>>,
+
+    /// `transmit` calls in progress, see `Connection::flush`
+    pub(crate) pending: Pending,
+
+    #[debug(skip)]
+    pub(crate) cmd_waiters: Mutex<Vec<Arc<CmdWaiter>>>,
+
+    #[debug(skip)]
+    pub(crate) key_press_forwarders: Forwarders<Keypress>,
+
+    #[debug(skip)]
+    pub(crate) cmd_forwarders: Forwarders<Cmd>,
+
+    #[debug(skip)]
+    pub(crate) log_msg_forwarders: Forwarders<LogMsg>,
+
+    /// This connection's callback table. libcec keeps a pointer to it, so it lives next to
+    /// the callbacks it dispatches to rather than in a shared static.
+    #[debug(skip)]
+    pub(crate) table: ICECCallbacks,
+}
+
+/// Sends an item to a subscriber, returning `false` once the subscriber is gone
+pub(crate) type Forward<T> = dyn Fn(&T) -> bool + Send;
+
+/// Channels handed out by `Connection::*_receiver`
+pub(crate) struct Forwarders<T>(Mutex<Vec<Box<Forward<T>>>>);
+
+/// A `transmit_and_wait` call waiting for its reply
+pub(crate) struct CmdWaiter {
+    initiator: LogicalAddress,
+    opcode: Opcode,
+    send: Box<dyn Fn(Result<Cmd>) + Send + Sync>,
 }
 
 pub type OnKeyPress = dyn FnMut(Keypress) + Send;
+pub type OnRawKeyPress = dyn FnMut(RawKeypress) + Send;
 pub type OnCmd = dyn FnMut(Cmd) + Send;
 pub type OnLogMsg = dyn FnMut(LogMsg) + Send;
 pub type OnSourceActivated = dyn FnMut(KnownLogicalAddress, bool) + Send;
-pub type OnCfgChanged = dyn FnMut(Cfg) + Send;
+pub type OnCfgChanged = dyn FnMut(Configuration) + Send;
 pub type OnAlert = dyn FnMut(Alert) + Send;
 pub type OnMenuStateChanged = dyn FnMut(MenuState) + Send;
+pub type OnEvent = dyn FnMut(Event) + Send;
 
-static mut CALLBACKS: ICECCallbacks = ICECCallbacks {
+/// Callback table registered with libcec, copied into each connection's `Callbacks`
+const CALLBACK_TABLE: ICECCallbacks = ICECCallbacks {
     logMessage: Some(callback::on_log_msg),
     keyPress: Some(callback::on_key_press),
     commandReceived: Some(callback::on_cmd_received),
@@ -224,52 +503,121 @@ static mut CALLBACKS: ICECCallbacks = ICECCallbacks {
     sourceActivated: Some(callback::on_source_activated),
 };
 
-#[derive(Builder, derive_more::Debug)]
-#[builder(
-    pattern = "owned",
-    build_fn(private, name = "build", error = "CfgBuilderError")
+#[derive(derive_more::Debug)]
+#[cfg_attr(
+    feature = "builder",
+    derive(Builder),
+    builder(
+        pattern = "owned",
+        build_fn(private, name = "build", error = "CfgBuilderError")
+    )
 )]
 pub struct Cfg {
     #[debug(skip)]
-    #[builder(default, setter(strip_option), pattern = "owned")]
+    #[cfg_attr(
+        feature = "builder",
+        builder(default, setter(strip_option), pattern = "owned")
+    )]
     on_key_press: Option<Box<OnKeyPress>>,
 
+    /// Receives every keypress, including keycodes that `on_key_press` drops because
+    /// `UserControlCode` doesn't know them.
+    #[debug(skip)]
+    #[cfg_attr(
+        feature = "builder",
+        builder(default, setter(strip_option), pattern = "owned")
+    )]
+    on_raw_key_press: Option<Box<OnRawKeyPress>>,
+
     #[debug(skip)]
-    #[builder(default, setter(strip_option), pattern = "owned")]
+    #[cfg_attr(
+        feature = "builder",
+        builder(default, setter(strip_option), pattern = "owned")
+    )]
     on_command_received: Option<Box<OnCmd>>,
 
+    /// Receives libcec's log messages. libcec logs a lot at `LogLevel::All`, when neither
+    /// this, `on_event` nor a `log_message_receiver` is set the messages are dropped before
+    /// being decoded.
     #[debug(skip)]
-    #[builder(default, setter(strip_option), pattern = "owned")]
+    #[cfg_attr(
+        feature = "builder",
+        builder(default, setter(strip_option), pattern = "owned")
+    )]
     on_log_message: Option<Box<OnLogMsg>>,
 
     #[debug(skip)]
-    #[builder(default, setter(strip_option), pattern = "owned")]
+    #[cfg_attr(
+        feature = "builder",
+        builder(default, setter(strip_option), pattern = "owned")
+    )]
     on_cfg_changed: Option<Box<OnCfgChanged>>,
 
     #[debug(skip)]
-    #[builder(default, setter(strip_option), pattern = "owned")]
+    #[cfg_attr(
+        feature = "builder",
+        builder(default, setter(strip_option), pattern = "owned")
+    )]
     on_alert: Option<Box<OnAlert>>,
 
     #[debug(skip)]
-    #[builder(default, setter(strip_option), pattern = "owned")]
+    #[cfg_attr(
+        feature = "builder",
+        builder(default, setter(strip_option), pattern = "owned")
+    )]
     on_menu_state_change: Option<Box<OnMenuStateChanged>>,
 
     #[debug(skip)]
-    #[builder(default, setter(strip_option), pattern = "owned")]
+    #[cfg_attr(
+        feature = "builder",
+        builder(default, setter(strip_option), pattern = "owned")
+    )]
     on_source_activated: Option<Box<OnSourceActivated>>,
 
-    #[builder(default)]
+    /// Receives keypresses, commands, log messages, alerts and source activations as one
+    /// `Event`. Called in addition to the callbacks for the individual kinds.
+    #[debug(skip)]
+    #[cfg_attr(
+        feature = "builder",
+        builder(default, setter(strip_option), pattern = "owned")
+    )]
+    on_event: Option<Box<OnEvent>>,
+
+    /// Port of the adapter, passed to libcec as is. Either a serial port, e.g.
+    /// `/dev/ttyACM0` or `COM3`, or a `tcp://host:port` address of a network CEC bridge, see
+    /// `CfgBuilder::network_port`.
+    #[cfg_attr(feature = "builder", builder(default))]
     device: Option<String>,
 
-    #[builder(default, setter(strip_option))]
+    #[cfg_attr(feature = "builder", builder(default, setter(strip_option)))]
     detect_device: Option<bool>,
 
-    #[builder(default = "Duration::from_secs(5)")]
+    /// Timeout for opening the adapter. Also bounds adapter detection when
+    /// `detect_device` is set.
+    #[cfg_attr(feature = "builder", builder(default = "Duration::from_secs(5)"))]
     timeout: Duration,
 
+    /// Make this device the active source again whenever the TV is observed
+    /// transitioning from standby to on, e.g. when it is turned on with its own remote.
+    /// The TV is seen waking when it reports its power status as on, or sends
+    /// `RequestActiveSource`, `RoutingChange` or `ActiveSource`, after it went to standby.
+    ///
+    /// Note that if another source on the bus does the same, the two devices will keep
+    /// taking the active source from each other.
+    #[cfg_attr(feature = "builder", builder(default, setter(strip_option)))]
+    reclaim_active_source_on_tv_wake: Option<bool>,
+
+    /// Poll a device periodically after connecting, set with `CfgBuilder::keepalive`
+    #[cfg_attr(
+        feature = "builder",
+        builder(default, private, setter(strip_option, name = "keepalive_value"))
+    )]
+    keepalive: Option<Keepalive>,
+
     //
     // cec_configuration items follow up
     //
+    /// The name of the device, as shown on the TV. At most `OSD_NAME_MAX_LEN` bytes.
     name: String,
 
     ///< the device type(s) to use on the CEC bus for libCEC.
@@ -277,326 +625,1188 @@ pub struct Cfg {
 
     // optional cec_configuration items follow
     ///< the physical address of the CEC adapter.
-    #[builder(default, setter(strip_option))]
-    physical_address: Option<u16>,
+    #[cfg_attr(feature = "builder", builder(default, setter(strip_option)))]
+    physical_address: Option<PhysicalAddress>,
 
     ///< the logical address of the device to which the adapter is connected. only used when iPhysicalAddress = 0 or when the adapter doesn't support autodetection.
-    #[builder(default, setter(strip_option))]
+    #[cfg_attr(feature = "builder", builder(default, setter(strip_option)))]
     base_device: Option<LogicalAddress>,
 
     ///< the HDMI port to which the adapter is connected. only used when iPhysicalAddress = 0 or when the adapter doesn't support autodetection.
-    #[builder(default, setter(strip_option))]
+    #[cfg_attr(feature = "builder", builder(default, setter(strip_option)))]
     hdmi_port: Option<u8>,
 
     ///< override the vendor ID of the TV. leave this untouched to autodetect.
-    #[builder(default, setter(strip_option))]
+    #[cfg_attr(feature = "builder", builder(default, setter(strip_option)))]
     tv_vendor: Option<u32>,
 
     ///< list of devices to wake when initialising libCEC or when calling PowerOnDevices() without any parameter..
-    #[builder(default, setter(strip_option))]
+    #[cfg_attr(feature = "builder", builder(default, setter(strip_option)))]
     wake_devices: Option<LogicalAddresses>,
 
     /// List of devices to power off when calling StandbyDevices() without any parameter.
-    #[builder(default, setter(strip_option))]
+    #[cfg_attr(feature = "builder", builder(default, setter(strip_option)))]
     power_off_devices: Option<LogicalAddresses>,
 
     /// True to get the settings from the ROM (if set, and a v2 ROM is present), false to use these settings.
-    #[builder(default, setter(strip_option))]
+    #[cfg_attr(feature = "builder", builder(default, setter(strip_option)))]
     settings_from_rom: Option<bool>,
 
     /// Make libCEC the active source on the bus when starting the player application.
-    #[builder(default, setter(strip_option))]
+    #[cfg_attr(feature = "builder", builder(default, setter(strip_option)))]
     activate_source: Option<bool>,
 
     /// Put this PC in standby mode when the TV is switched off.
     /// Only used when `bShutdownOnStandby` = 0.
-    #[builder(default, setter(strip_option))]
+    #[cfg_attr(feature = "builder", builder(default, setter(strip_option)))]
     power_off_on_standby: Option<bool>,
 
     /// The menu language used by the client. 3 character ISO 639-2 country code. see http://http://www.loc.gov/standards/iso639-2/ added in 1.6.2.
-    #[builder(default, setter(strip_option))]
-    language: Option<String>,
+    /// Use `try_language` to set it from a string.
+    #[cfg_attr(
+        feature = "builder",
+        builder(default, setter(strip_option), try_setter)
+    )]
+    language: Option<MenuLanguage>,
 
     /// Won't allocate a CCECClient when starting the connection when set (same as monitor mode). added in 1.6.3.
-    #[builder(default, setter(strip_option))]
+    #[cfg_attr(feature = "builder", builder(default, setter(strip_option)))]
     monitor_only: Option<bool>,
 
     /// Type of the CEC adapter that we're connected to. added in 1.8.2.
-    #[builder(default, setter(strip_option))]
+    /// With `detect_device`, an adapter of this type is preferred over the first one found.
+    #[cfg_attr(feature = "builder", builder(default, setter(strip_option)))]
     adapter_type: Option<AdapterType>,
 
     /// key code that initiates combo keys. defaults to CEC_USER_CONTROL_CODE_F1_BLUE. CEC_USER_CONTROL_CODE_UNKNOWN to disable. added in 2.0.5.
-    #[builder(default, setter(strip_option))]
+    /// `CfgBuilder::disable_combo_key` disables them.
+    #[cfg_attr(feature = "builder", builder(default, setter(strip_option)))]
     combo_key: Option<UserControlCode>,
 
     /// Timeout until the combo key is sent as normal keypress.
-    #[builder(default, setter(strip_option))]
+    #[cfg_attr(feature = "builder", builder(default, setter(strip_option)))]
     combo_key_timeout: Option<Duration>,
 
     /// Rate at which buttons autorepeat. 0 means rely on CEC device.
-    #[builder(default, setter(strip_option))]
+    #[cfg_attr(feature = "builder", builder(default, setter(strip_option)))]
     button_repeat_rate: Option<Duration>,
 
     /// Duration after last update until a button is considered released.
-    #[builder(default, setter(strip_option))]
+    #[cfg_attr(feature = "builder", builder(default, setter(strip_option)))]
     button_release_delay: Option<Duration>,
 
     /// Prevent double taps within this timeout. defaults to 200ms. added in 4.0.0.
-    #[builder(default, setter(strip_option))]
+    #[cfg_attr(feature = "builder", builder(default, setter(strip_option)))]
     double_tap_timeout: Option<Duration>,
 
     /// Set to 1 to automatically waking an AVR when the source is activated. added in 4.0.0.
-    #[builder(default, setter(strip_option))]
+    #[cfg_attr(feature = "builder", builder(default, setter(strip_option)))]
     autowake_avr: Option<bool>,
 }
 
+/// Read-only view of the configuration libcec is actually using, as reported back by libcec
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Configuration {
+    /// The name of the device, as shown on the TV.
+    pub name: String,
+    /// The device type(s) used on the CEC bus.
+    pub kinds: DeviceKinds,
+    /// True when the physical address was autodetected.
+    pub autodetect_address: bool,
+    /// The physical address of the CEC adapter.
+    pub physical_address: u16,
+    /// The logical address of the device to which the adapter is connected.
+    pub base_device: LogicalAddress,
+    /// The HDMI port to which the adapter is connected.
+    pub hdmi_port: u8,
+    /// The vendor ID of the TV.
+    pub tv_vendor: u32,
+    /// Devices to wake when initialising libCEC or when calling PowerOnDevices() without any parameter.
+    pub wake_devices: LogicalAddresses,
+    /// Devices to power off when calling StandbyDevices() without any parameter.
+    pub power_off_devices: LogicalAddresses,
+    /// The version of libCEC.
+    pub server_version: u32,
+    /// True when the settings are read from the ROM.
+    pub settings_from_rom: bool,
+    /// Make libCEC the active source on the bus when starting the player application.
+    pub activate_source: bool,
+    /// Put this PC in standby mode when the TV is switched off.
+    pub power_off_on_standby: bool,
+    /// The logical addresses that libCEC claimed on the bus.
+    pub logical_addresses: LogicalAddresses,
+    /// The menu language used by the client. 3 character ISO 639-2 country code.
+    pub language: String,
+    /// True when no CCECClient was allocated (monitor mode).
+    pub monitor_only: bool,
+    /// CEC spec version that libCEC uses.
+    pub cec_version: Version,
+    /// Type of the CEC adapter that we're connected to.
+    pub adapter_type: AdapterType,
+    /// Key code that initiates combo keys.
+    pub combo_key: UserControlCode,
+    /// Timeout until the combo key is sent as normal keypress.
+    pub combo_key_timeout: Duration,
+    /// Rate at which buttons autorepeat. 0 means rely on CEC device.
+    pub button_repeat_rate: Duration,
+    /// Duration after last update until a button is considered released.
+    pub button_release_delay: Duration,
+    /// Prevent double taps within this timeout.
+    pub double_tap_timeout: Duration,
+    /// True when an AVR is woken automatically when the source is activated.
+    pub autowake_avr: bool,
+    /// Firmware version of the adapter, 0 before the adapter is opened.
+    pub firmware_version: u16,
+    /// Firmware build date of the adapter, `None` when the adapter doesn't report one.
+    pub firmware_build_date: Option<SystemTime>,
+}
+
+/// CEC adapter found by `detect_adapters`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdapterDescriptor {
+    /// Name of the port, pass this as the `device` when connecting.
+    pub com_name: String,
+    /// Path of the device.
+    pub com_path: String,
+    /// USB vendor ID of the adapter.
+    pub vendor_id: u16,
+    /// USB product ID of the adapter.
+    pub product_id: u16,
+    /// Firmware version of the adapter, 0 when not queried.
+    pub firmware_version: u16,
+    /// Physical address of the adapter, 0 when not queried.
+    pub physical_address: u16,
+    /// Firmware build date of the adapter as a unix timestamp, 0 when not queried.
+    pub firmware_build_date: u32,
+    /// Type of the adapter.
+    pub adapter_type: AdapterType,
+}
+
+#[cfg(feature = "builder")]
 impl CfgBuilder {
+    /// Builder preconfigured for passively monitoring the bus
+    ///
+    /// Sets `monitor_only`, so libcec does not allocate a CCECClient: the connection does
+    /// not claim a logical address and methods that transmit will fail. Received commands
+    /// and log messages are still delivered. The device kind is only a placeholder.
+    pub fn monitor_preset(name: String) -> Self {
+        Self::default()
+            .name(name)
+            .kind(DeviceKind::RecordingDevice)
+            .monitor_only(true)
+            .activate_source(false)
+    }
+
+    /// Set `on_command_received` to `callback`, called only for commands with one of
+    /// `opcodes`
+    ///
+    /// Replaces the `on_command_received` callback.
+    pub fn command_callback_for(self, opcodes: HashSet<Opcode>, mut callback: Box<OnCmd>) -> Self {
+        self.on_command_received(Box::new(move |command: Cmd| {
+            if opcodes.contains(&command.opcode) {
+                callback(command)
+            }
+        }))
+    }
+
+    /// Forward libcec's log messages to the `log` crate under the `libcec` target
+    ///
+    /// Replaces the `on_log_message` callback.
+    pub fn forward_logs_to_log_crate(self) -> Self {
+        self.on_log_message(Box::new(|message: LogMsg| {
+            log::log!(target: "libcec", message.level.into(), "{}", message.message)
+        }))
+    }
+
+    /// Disable combo keys, setting `combo_key` to `UserControlCode::Unknown`
+    ///
+    /// By default libcec uses `UserControlCode::F1Blue` as the combo key: it holds the key back
+    /// for `combo_key_timeout` to see whether another key follows.
+    pub fn disable_combo_key(self) -> Self {
+        self.combo_key(UserControlCode::Unknown)
+    }
+
+    /// Connect to a network CEC bridge listening on `host`:`port`
+    ///
+    /// Sets `device` to the `tcp://host:port` address libcec expects.
+    pub fn network_port(self, host: &str, port: u16) -> Self {
+        self.device(Some(format!("tcp://{host}:{port}")))
+    }
+
+    /// Poll `target` every `interval` while connected, for TVs that drop the CEC link
+    /// after being idle
+    ///
+    /// The polls run on a background thread spawned once the adapter is open. Failed polls
+    /// are logged and polling continues. Closing or dropping the connection stops the
+    /// thread without waiting for the next interval, and joins it before the adapter is
+    /// closed. `connect` rejects a zero `interval`.
+    pub fn keepalive(self, interval: Duration, target: LogicalAddress) -> Self {
+        self.keepalive_value(Keepalive { interval, target })
+    }
+
     pub fn connect(self) -> Result<Connection> {
         let cfg = self.build()?;
         cfg.connect()
     }
+
+    /// See `Cfg::connect_virtual`
+    pub fn connect_virtual(self) -> Result<VirtualCecDevice> {
+        let cfg = self.build()?;
+        cfg.connect_virtual()
+    }
 }
 
+/// An open libcec connection
+///
+/// A `Connection` may be moved and shared between threads freely, the callbacks stay
+/// registered with libcec. Closing it with `close` or dropping it first stops the
+/// keepalive thread, then waits for the commands queued with `transmit_async` to be
+/// transmitted, and only then closes the adapter.
 #[derive(Debug)]
-pub struct Connection(pub Cfg, pub libcec_connection_t, pub Pin<Box<Callbacks>>);
+pub struct Connection(
+    // Not referenced by libcec, may change freely
+    Cfg,
+    libcec_connection_t,
+    // libcec holds raw pointers into the pinned callbacks for as long as the connection is
+    // open: the callback table and the user data passed back to every callback. Moving the
+    // `Connection` is fine, the box keeps its address. Replacing or swapping this field is
+    // not, libcec would keep dispatching to the old allocation.
+    Pin<Box<Callbacks>>,
+    // Shares the handle with its worker thread, shut down before the handle is closed
+    TransmitQueue,
+    // Shares the handle, shut down before the handle is closed
+    Option<KeepaliveThread>,
+);
 unsafe impl Send for Connection {}
+// SAFETY: methods taking `&self` only pass the handle to libcec, touch the mutex guarded
+// waiters, forwarders and pending counter of `.2`, or read the plain data of `.0` or the
+// queue of `.3`.
+// Concurrent calls on one handle are safe because libcec locks the state behind its C API:
+// `CCECProcessor::m_mutex` is held while transmitting and while reading or changing the
+// logical addresses, `CCECClient::m_mutex` guards the client configuration, and each
+// `CCECBusDevice` guards its own state (power status, vendor, OSD name, ...) with its
+// `m_mutex`. libcec's own reader and processor threads already call into these objects
+// concurrently with the client.
+// The boxed callbacks in `.0` are moved into `.2` on connect. libcec's threads only get
+// `&Callbacks` from the registered user data pointer, the same shared access `&Connection`
+// methods have: every `FnMut` slot and the active source reclaim state sit behind a
+// `Mutex`, and the waiters and forwarders behind their own, so concurrent callbacks and
+// methods never alias a `&mut`. No `&mut Callbacks` is created while libcec may call back.
+unsafe impl Sync for Connection {}
 
 impl Connection {
+    #[cfg(feature = "builder")]
     pub fn builder() -> CfgBuilder {
         CfgBuilder::default()
     }
 
-    pub fn transmit(&self, command: Cmd) -> Result<()> {
-        if unsafe { libcec_transmit(self.1, &command.into()) } == 0 {
-            Err(ConnectionError::TransmitFailed.into())
-        } else {
-            Ok(())
+    /// Close the adapter and return the configuration it was opened with, e.g. to release the
+    /// serial port for another process and reopen it later with `Cfg::connect`
+    ///
+    /// The callbacks are handed back to the configuration. Receivers from `*_receiver` are
+    /// disconnected.
+    pub fn close(self) -> Cfg {
+        // Drop must not run, it would close and destroy the handle a second time
+        let connection = ManuallyDrop::new(self);
+        // SAFETY: as below, `.3` and `.4` are moved out once. They must be gone before the
+        // handle is closed.
+        drop(unsafe { ptr::read(&connection.4) });
+        drop(unsafe { ptr::read(&connection.3) });
+        unsafe {
+            libcec_close(connection.1);
+            libcec_destroy(connection.1);
         }
+        // SAFETY: `connection` is never dropped or used again, so each field is moved out once.
+        // libcec is destroyed, nothing refers to the callbacks anymore.
+        let (mut cfg, callbacks) = unsafe { (ptr::read(&connection.0), ptr::read(&connection.2)) };
+        let callbacks = *Pin::into_inner(callbacks);
+        cfg.on_key_press = callbacks.on_key_press.into_inner().unwrap();
+        cfg.on_raw_key_press = callbacks.on_raw_key_press.into_inner().unwrap();
+        cfg.on_command_received = callbacks.on_cmd_received.into_inner().unwrap();
+        cfg.on_log_message = callbacks.on_log_msg.into_inner().unwrap();
+        cfg.on_cfg_changed = callbacks.on_cfg_changed.into_inner().unwrap();
+        cfg.on_alert = callbacks.on_alert.into_inner().unwrap();
+        cfg.on_menu_state_change = callbacks.on_menu_state_changed.into_inner().unwrap();
+        cfg.on_source_activated = callbacks.on_source_activated.into_inner().unwrap();
+        cfg.on_event = callbacks.on_event.into_inner().unwrap();
+        cfg
     }
-    pub fn send_power_on_devices(&self, address: LogicalAddress) -> Result<()> {
-        if unsafe { libcec_power_on_devices(self.1, address.repr()) } == 0 {
-            Err(ConnectionError::TransmitFailed.into())
-        } else {
-            Ok(())
-        }
+
+    /// The libcec handle, for calling `cec_sys` functions this crate does not wrap
+    ///
+    /// # Safety
+    ///
+    /// The handle is owned by this connection and only valid while it is alive: it is closed
+    /// and destroyed on drop and on `close`, so it must not be used afterwards. Do not call
+    /// `libcec_close`, `libcec_destroy` or `libcec_set_callbacks` on it, nor change the
+    /// callbacks through `libcec_set_configuration`: libcec dispatches callbacks to this
+    /// connection's callback table and user data.
+    pub unsafe fn raw_handle(&self) -> libcec_connection_t {
+        self.1
     }
-    pub fn send_standby_devices(&self, address: LogicalAddress) -> Result<()> {
-        if unsafe { libcec_standby_devices(self.1, address.repr()) } == 0 {
-            Err(ConnectionError::TransmitFailed.into())
-        } else {
+
+    /// Port the adapter was opened on, e.g. `/dev/ttyACM0`
+    ///
+    /// With `detect_device` this is the port libcec detected, pass it as `device` to reconnect
+    /// to the same adapter later.
+    pub fn port(&self) -> Option<&str> {
+        self.0.device.as_deref()
+    }
+
+    /// Send `command` on the bus
+    ///
+    /// A destination of `LogicalAddress::BROADCAST` (15, the same value as `Unregistered`)
+    /// broadcasts the command to every device.
+    pub fn transmit(&self, command: Cmd) -> Result<()> {
+        let _pending = self.2.pending.start();
+        transmit_command(self.1, &command)
+            .map_err(|failure| ConnectionError::TransmitFailed(failure).into())
+    }
+    /// Wait up to `timeout` for the commands being transmitted to finish, e.g. before
+    /// dropping the connection on shutdown, so that a `Standby` queued with `transmit_async`
+    /// or sent from another thread is not cut off
+    ///
+    /// Flushed means at the bus level: every command queued with `transmit_async` and every
+    /// `transmit` call in progress, including those started while waiting, was handed to
+    /// libcec and libcec returned, i.e. the frame was acknowledged or transmitting it
+    /// failed. Completions of queued commands have been delivered to
+    /// `transmit_completion_receiver` by then. Nothing is promised for libcec's own internal
+    /// retries or the frames it sends by itself, nor that the destination acted on the
+    /// command.
+    ///
+    /// # Errors
+    ///
+    /// - Timeout: commands are still queued or being transmitted after `timeout`
+    pub fn flush(&self, timeout: Duration) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        // The worker of the queue calls libcec directly, it is not counted as a `transmit`
+        if self.3.flush(timeout)
+            && self
+                .2
+                .pending
+                .wait(deadline.saturating_duration_since(Instant::now()))
+        {
             Ok(())
+        } else {
+            Err(ConnectionError::Timeout.into())
         }
     }
 
-    pub fn set_active_source(&self, device_type: DeviceKind) -> Result<()> {
-        if unsafe { libcec_set_active_source(self.1, device_type.repr()) } == 0 {
-            Err(ConnectionError::TransmitFailed.into())
-        } else {
-            Ok(())
+    /// Send a libcec command built with `Cmd::raw`, e.g. with an opcode `Opcode` does not know
+    ///
+    /// # Errors
+    ///
+    /// - TransmitFailed: cec_sys::libcec_transmit fails
+    pub fn transmit_raw(&self, command: cec_command) -> Result<()> {
+        debug!(
+            "transmit_raw: {:?} -> {:?}, opcode {:#04x}",
+            command.initiator, command.destination, command.opcode
+        );
+        let mut failure = TransmitFailure::new("libcec_transmit");
+        if let Some(address) = LogicalAddress::from_repr(command.destination) {
+            failure = failure.with_address(address);
         }
+        transmit_result(unsafe { libcec_transmit(self.1, &command) }, failure)
     }
 
-    pub fn get_active_source(&self) -> LogicalAddress {
-        let active_raw: cec_logical_address = unsafe { libcec_get_active_source(self.1) };
-        LogicalAddress::from_repr(active_raw).unwrap()
+    /// Transmit a command from this device's primary logical address
+    ///
+    /// # Errors
+    ///
+    /// - PrimaryAddressUnregistered: this device has not claimed a logical address
+    /// - TooManyParameters: `parameters` is longer than a `DataPacket`
+    /// - TransmitFailed: cec_sys::libcec_transmit fails
+    pub fn transmit_from_primary(
+        &self,
+        destination: LogicalAddress,
+        opcode: Opcode,
+        parameters: &[u8],
+    ) -> Result<()> {
+        let primary = self.registered_primary()?;
+        self.transmit(Cmd::try_new(primary, destination, opcode, parameters)?)
     }
 
-    pub fn is_active_source(&self, address: LogicalAddress) -> Result<()> {
-        if unsafe { libcec_is_active_source(self.1, address.repr()) } == 0 {
-            Err(ConnectionError::TransmitFailed.into())
-        } else {
-            Ok(())
+    fn registered_primary(&self) -> Result<LogicalAddress> {
+        let primary = LogicalAddress::from(self.get_logical_addresses()?.primary);
+        if primary == LogicalAddress::Unregistered {
+            return Err(ConnectionError::PrimaryAddressUnregistered.into());
         }
+        Ok(primary)
     }
 
-    pub fn get_device_power_status(&self, address: LogicalAddress) -> PowerStatus {
-        let status_raw: cec_power_status =
-            unsafe { libcec_get_device_power_status(self.1, address.repr()) };
+    /// Transmit `command` and wait for a reply with `expected_opcode` from its destination,
+    /// e.g. `ReportPowerStatus` for `GiveDevicePowerStatus`
+    ///
+    /// The reply is also passed to the `on_command_received` callback as usual.
+    ///
+    /// # Errors
+    ///
+    /// - TransmitFailed: transmitting `command` fails
+    /// - Timeout: no reply is received within `timeout`
+    /// - Cancelled: `abort_pending` was called before the reply arrived
+    pub fn transmit_and_wait(
+        &self,
+        command: Cmd,
+        expected_opcode: Opcode,
+        timeout: Duration,
+    ) -> Result<Cmd> {
+        let (sender, receiver) = mpsc::channel();
+        let waiter = self.register_waiter(
+            &command,
+            expected_opcode,
+            // Fails if the waiter already timed out, nothing to do then
+            Box::new(move |reply| drop(sender.send(reply))),
+        );
 
-        PowerStatus::from_repr(status_raw).unwrap()
+        let reply = self.transmit(command).and_then(|_| {
+            receiver
+                .recv_timeout(timeout)
+                .map_err(|_| ConnectionError::Timeout)?
+        });
+
+        self.unregister_waiter(&waiter);
+        reply
     }
 
-    pub fn send_keypress(
+    /// Register for the reply to `command`. Must happen before transmitting, the reply may
+    /// arrive before transmit returns.
+    pub(crate) fn register_waiter(
         &self,
-        address: LogicalAddress,
-        key: UserControlCode,
-        wait: bool,
-    ) -> Result<()> {
-        if unsafe { libcec_send_keypress(self.1, address.repr(), key.repr(), wait.into()) } == 0 {
-            Err(ConnectionError::TransmitFailed.into())
-        } else {
-            Ok(())
-        }
+        command: &Cmd,
+        expected_opcode: Opcode,
+        send: Box<dyn Fn(Result<Cmd>) + Send + Sync>,
+    ) -> Arc<CmdWaiter> {
+        let waiter = Arc::new(CmdWaiter {
+            initiator: command.destination,
+            opcode: expected_opcode,
+            send,
+        });
+        self.2.cmd_waiters.lock().unwrap().push(waiter.clone());
+        waiter
     }
 
-    pub fn send_key_release(&self, address: LogicalAddress, wait: bool) -> Result<()> {
-        if unsafe { libcec_send_key_release(self.1, address.repr(), wait.into()) } == 0 {
-            Err(ConnectionError::TransmitFailed.into())
-        } else {
-            Ok(())
-        }
+    pub(crate) fn unregister_waiter(&self, waiter: &Arc<CmdWaiter>) {
+        self.2
+            .cmd_waiters
+            .lock()
+            .unwrap()
+            .retain(|x| !Arc::ptr_eq(x, waiter));
     }
 
-    pub fn volume_up(&self, send_release: bool) -> Result<()> {
-        if unsafe { libcec_volume_up(self.1, send_release.into()) } == 0 {
-            Err(ConnectionError::TransmitFailed.into())
-        } else {
-            Ok(())
+    /// Wake every pending `transmit_and_wait` call with a `Cancelled` error, e.g. when
+    /// shutting down
+    ///
+    /// Only calls already waiting are affected. A call that is still transmitting returns
+    /// `Cancelled` once the transmit finishes, without waiting for the reply.
+    pub fn abort_pending(&self) {
+        let waiters = std::mem::take(&mut *self.2.cmd_waiters.lock().unwrap());
+        debug!("abort_pending: cancelling {} waiters", waiters.len());
+        for waiter in waiters {
+            (waiter.send)(Err(ConnectionError::Cancelled.into()));
         }
     }
 
-    pub fn volume_down(&self, send_release: bool) -> Result<()> {
-        if unsafe { libcec_volume_down(self.1, send_release.into()) } == 0 {
-            Err(ConnectionError::TransmitFailed.into())
-        } else {
-            Ok(())
-        }
+    /// Receive every decoded command from the bus through a channel
+    ///
+    /// Receivers are independent of each other and of the `on_command_received` callback,
+    /// all of them get every command. Dropping the receiver unsubscribes it. The channel is
+    /// unbounded, so commands pile up if the receiver is not drained.
+    pub fn command_receiver(&self) -> mpsc::Receiver<Cmd> {
+        self.2.cmd_forwarders.subscribe()
     }
 
-    pub fn mute_audio(&self, send_release: bool) -> Result<()> {
-        if unsafe { libcec_mute_audio(self.1, send_release.into()) } == 0 {
-            Err(ConnectionError::TransmitFailed.into())
-        } else {
-            Ok(())
-        }
+    /// Receive every keypress through a channel, see `command_receiver`
+    pub fn keypress_receiver(&self) -> mpsc::Receiver<Keypress> {
+        self.2.key_press_forwarders.subscribe()
     }
 
-    pub fn audio_toggle_mute(&self) -> Result<()> {
-        if unsafe { libcec_audio_toggle_mute(self.1) } == 0 {
-            Err(ConnectionError::TransmitFailed.into())
-        } else {
-            Ok(())
-        }
+    /// Receive every log message through a channel, see `command_receiver`
+    pub fn log_message_receiver(&self) -> mpsc::Receiver<LogMsg> {
+        self.2.log_msg_forwarders.subscribe()
     }
 
-    pub fn audio_mute(&self) -> Result<()> {
-        if unsafe { libcec_audio_mute(self.1) } == 0 {
-            Err(ConnectionError::TransmitFailed.into())
-        } else {
-            Ok(())
-        }
+    /// Set or replace the `on_key_press` callback of the open connection
+    ///
+    /// libcec's callbacks are disabled while the callback is swapped, waiting for a running
+    /// callback to return, and registered again afterwards. Events arriving in between are
+    /// not delivered to any callback or receiver.
+    ///
+    /// # Errors
+    ///
+    /// - CallbackRegistrationFailed: cec_sys::libcec_set_callbacks fails, no callback is
+    ///   called until one of these setters succeeds
+    pub fn set_key_press_callback(&mut self, callback: Box<OnKeyPress>) -> Result<()> {
+        self.replace_callbacks(|callbacks| *callbacks.on_key_press.lock().unwrap() = Some(callback))
     }
 
-    pub fn audio_unmute(&self) -> Result<()> {
-        if unsafe { libcec_audio_unmute(self.1) } == 0 {
-            Err(ConnectionError::TransmitFailed.into())
-        } else {
-            Ok(())
-        }
+    /// Set or replace the `on_command_received` callback, see `set_key_press_callback`
+    pub fn set_command_callback(&mut self, callback: Box<OnCmd>) -> Result<()> {
+        self.replace_callbacks(|callbacks| {
+            *callbacks.on_cmd_received.lock().unwrap() = Some(callback)
+        })
     }
 
-    pub fn audio_get_status(&self) -> Result<()> {
-        if unsafe { libcec_audio_get_status(self.1) } == 0 {
-            Err(ConnectionError::TransmitFailed.into())
-        } else {
-            Ok(())
-        }
+    /// Set or replace the `on_log_message` callback, see `set_key_press_callback`
+    pub fn set_log_callback(&mut self, callback: Box<OnLogMsg>) -> Result<()> {
+        self.replace_callbacks(|callbacks| *callbacks.on_log_msg.lock().unwrap() = Some(callback))
     }
 
-    pub fn set_inactive_view(&self) -> Result<()> {
-        if unsafe { libcec_set_inactive_view(self.1) } == 0 {
-            Err(ConnectionError::TransmitFailed.into())
-        } else {
-            Ok(())
-        }
+    /// Remove the `on_key_press` callback, see `set_key_press_callback`
+    ///
+    /// Only the callback stops, receivers and the `on_event` callback still get keypresses.
+    pub fn clear_key_press_callback(&mut self) -> Result<()> {
+        self.replace_callbacks(|callbacks| *callbacks.on_key_press.lock().unwrap() = None)
     }
 
-    pub fn set_logical_address(&self, address: LogicalAddress) -> Result<()> {
-        if unsafe { libcec_set_logical_address(self.1, address.repr()) } == 0 {
-            Err(ConnectionError::TransmitFailed.into())
-        } else {
-            Ok(())
-        }
+    /// Remove the `on_command_received` callback, see `clear_key_press_callback`
+    ///
+    /// Unlike `switch_monitoring(false)`, this only affects this callback: replies to
+    /// `transmit_and_wait` and the receivers still get commands.
+    pub fn clear_command_callback(&mut self) -> Result<()> {
+        self.replace_callbacks(|callbacks| *callbacks.on_cmd_received.lock().unwrap() = None)
     }
 
-    pub fn switch_monitoring(&self, enable: bool) -> Result<()> {
-        if unsafe { libcec_switch_monitoring(self.1, enable.into()) } == 0 {
-            Err(ConnectionError::TransmitFailed.into())
-        } else {
-            Ok(())
-        }
+    /// Remove the `on_log_message` callback, see `clear_key_press_callback`
+    pub fn clear_log_callback(&mut self) -> Result<()> {
+        self.replace_callbacks(|callbacks| *callbacks.on_log_msg.lock().unwrap() = None)
     }
 
-    pub fn get_logical_addresses(&self) -> Result<LogicalAddresses> {
-        LogicalAddresses::try_from(unsafe { libcec_get_logical_addresses(self.1) })
+    /// Run `replace` on the callbacks while libcec cannot call them
+    ///
+    /// `libcec_disable_callbacks` takes the lock libcec holds while calling a callback, so no
+    /// callback runs concurrently with `replace`. Slots are changed through their mutexes
+    /// like the callbacks read them, never through `&mut Callbacks`, which would alias the
+    /// user data pointer libcec holds. The box is not moved, so that pointer stays valid.
+    fn replace_callbacks(&mut self, replace: impl FnOnce(&Callbacks)) -> Result<()> {
+        unsafe { libcec_disable_callbacks(self.1) };
+        replace(&self.2);
+        unsafe { callback::register_callbacks(self.1, &self.2) }
     }
 
-    // Unimplemented:
-    // extern DECLSPEC int libcec_set_physical_address(libcec_connection_t connection, uint16_t iPhysicalAddress);
-    // extern DECLSPEC int libcec_set_deck_control_mode(libcec_connection_t connection, CEC_NAMESPACE cec_deck_control_mode mode, int bSendUpdate);
-    // extern DECLSPEC int libcec_set_deck_info(libcec_connection_t connection, CEC_NAMESPACE cec_deck_info info, int bSendUpdate);
-    // extern DECLSPEC int libcec_set_menu_state(libcec_connection_t connection, CEC_NAMESPACE cec_menu_state state, int bSendUpdate);
-    // extern DECLSPEC int libcec_set_osd_string(libcec_connection_t connection, CEC_NAMESPACE cec_logical_address iLogicalAddress, CEC_NAMESPACE cec_display_control duration, const char* strMessage);
-    // extern DECLSPEC CEC_NAMESPACE cec_version libcec_get_device_cec_version(libcec_connection_t connection, CEC_NAMESPACE cec_logical_address iLogicalAddress);
-    // extern DECLSPEC int libcec_get_device_menu_language(libcec_connection_t connection, CEC_NAMESPACE cec_logical_address iLogicalAddress, CEC_NAMESPACE cec_menu_language language);
-    // extern DECLSPEC uint32_t libcec_get_device_vendor_id(libcec_connection_t connection, CEC_NAMESPACE cec_logical_address iLogicalAddress);
-    // extern DECLSPEC uint16_t libcec_get_device_physical_address(libcec_connection_t connection, CEC_NAMESPACE cec_logical_address iLogicalAddress);
-    // extern DECLSPEC int libcec_poll_device(libcec_connection_t connection, CEC_NAMESPACE cec_logical_address iLogicalAddress);
-    // extern DECLSPEC CEC_NAMESPACE cec_logical_addresses libcec_get_active_devices(libcec_connection_t connection);
-    // extern DECLSPEC int libcec_is_active_device(libcec_connection_t connection, CEC_NAMESPACE cec_logical_address address);
-    // extern DECLSPEC int libcec_is_active_device_type(libcec_connection_t connection, CEC_NAMESPACE cec_device_type type);
-    // extern DECLSPEC int libcec_set_hdmi_port(libcec_connection_t connection, CEC_NAMESPACE cec_logical_address baseDevice, uint8_t iPort);
-    // extern DECLSPEC int libcec_get_device_osd_name(libcec_connection_t connection, CEC_NAMESPACE cec_logical_address iAddress, CEC_NAMESPACE cec_osd_name name);
-    // extern DECLSPEC int libcec_set_stream_path_logical(libcec_connection_t connection, CEC_NAMESPACE cec_logical_address iAddress);
-    // extern DECLSPEC int libcec_set_stream_path_physical(libcec_connection_t connection, uint16_t iPhysicalAddress);
-    // extern DECLSPEC int libcec_get_current_configuration(libcec_connection_t connection, CEC_NAMESPACE libcec_configuration* configuration);
-    // extern DECLSPEC int libcec_can_persist_configuration(libcec_connection_t connection);
-    // extern DECLSPEC int libcec_persist_configuration(libcec_connection_t connection, CEC_NAMESPACE libcec_configuration* configuration);
-    // extern DECLSPEC int libcec_set_configuration(libcec_connection_t connection, const CEC_NAMESPACE libcec_configuration* configuration);
-    // extern DECLSPEC void libcec_rescan_devices(libcec_connection_t connection);
-    // extern DECLSPEC int libcec_is_libcec_active_source(libcec_connection_t connection);
-    // extern DECLSPEC int libcec_get_device_information(libcec_connection_t connection, const char* strPort, CEC_NAMESPACE libcec_configuration* config, uint32_t iTimeoutMs);
-    // extern DECLSPEC const char* libcec_get_lib_info(libcec_connection_t connection);
-    // extern DECLSPEC void libcec_init_video_standalone(libcec_connection_t connection);
-    // extern DECLSPEC uint16_t libcec_get_adapter_vendor_id(libcec_connection_t connection);
-    // extern DECLSPEC uint16_t libcec_get_adapter_product_id(libcec_connection_t connection);
-    // extern DECLSPEC int8_t libcec_detect_adapters(libcec_connection_t connection, CEC_NAMESPACE cec_adapter_descriptor* deviceList, uint8_t iBufSize, const char* strDevicePath, int bQuickScan);
-}
+    pub fn send_power_on_devices(&self, address: LogicalAddress) -> Result<()> {
+        debug!("send_power_on_devices: -> {address:?}");
+        transmit_result(
+            unsafe { libcec_power_on_devices(self.1, address.repr()) },
+            TransmitFailure::new("libcec_power_on_devices").with_address(address),
+        )
+    }
+    pub fn send_standby_devices(&self, address: LogicalAddress) -> Result<()> {
+        debug!("send_standby_devices: -> {address:?}");
+        transmit_result(
+            unsafe { libcec_standby_devices(self.1, address.repr()) },
+            TransmitFailure::new("libcec_standby_devices").with_address(address),
+        )
+    }
 
-impl Cfg {
-    /// Open connection to configuration represented by this object
-    ///
+    /// Send a polling message to `address`, which fails when no device acknowledges it
     ///
     /// # Errors
     ///
-    /// Error is returned in following cases
-    /// - LibInitFailed: cec_sys::libcec_initialise fails
-    /// - AdapterOpenFailed: cec_sys::libcec_open fails
-    /// - CallbackRegistrationFailed: cec_sys::libcec_enable_callbacks fails
-    pub fn connect(mut self) -> Result<Connection> {
-        let mut cfg: libcec_configuration = (&self).into();
-        // Consume self.*_callback and build CecCallbacks from those
-        let pinned_callbacks = Box::pin(Callbacks {
-            on_key_press: self.on_key_press.take(),
-            on_cmd_received: self.on_command_received.take(),
-            on_log_msg: self.on_log_message.take(),
-            on_cfg_changed: self.on_cfg_changed.take(),
-            on_alert: self.on_alert.take(),
-            on_menu_state_changed: self.on_menu_state_change.take(),
-            on_source_activated: self.on_source_activated.take(),
-        });
-        let rust_callbacks_as_void_ptr = &*pinned_callbacks as *const _ as *mut _;
-        let detect_device = self.detect_device.unwrap_or(false);
-        let device = self.device.clone();
-        let open_timeout = self.timeout.as_millis() as u32;
+    /// - TransmitFailed: cec_sys::libcec_poll_device fails
+    pub fn poll_device(&self, address: LogicalAddress) -> Result<()> {
+        poll_device(self.1, address)
+            .map_err(|failure| ConnectionError::TransmitFailed(failure).into())
+    }
 
-        let connection = Connection(
-            self,
-            unsafe { libcec_initialise(&mut cfg) },
-            pinned_callbacks,
-        );
+    /// Power on the devices in the configured `wake_devices`
+    ///
+    /// Calls `send_power_on_devices` with `LogicalAddress::BROADCAST`, which libcec expands
+    /// to the `wake_devices` list rather than a literal broadcast.
+    pub fn power_on_all(&self) -> Result<()> {
+        self.send_power_on_devices(LogicalAddress::BROADCAST)
+    }
 
-        if connection.1.is_null() {
+    /// Put the devices in the configured `power_off_devices` in standby
+    ///
+    /// Calls `send_standby_devices` with `LogicalAddress::BROADCAST`, which libcec expands
+    /// to the `power_off_devices` list rather than a literal broadcast.
+    pub fn standby_all(&self) -> Result<()> {
+        self.send_standby_devices(LogicalAddress::BROADCAST)
+    }
+
+    pub fn set_active_source(&self, device_type: DeviceKind) -> Result<()> {
+        transmit_result(
+            unsafe { libcec_set_active_source(self.1, device_type.repr()) },
+            TransmitFailure::new("libcec_set_active_source"),
+        )
+    }
+
+    /// Broadcast an `ActiveSource` command from the primary logical address, announcing
+    /// `address` as the active source
+    ///
+    /// Unlike `set_active_source`, which always announces this device's own physical
+    /// address, this announces any physical address, e.g. the input of a switch behind it.
+    ///
+    /// # Errors
+    ///
+    /// - PrimaryAddressUnregistered: this device has not claimed a logical address
+    /// - TransmitFailed: cec_sys::libcec_transmit fails
+    pub fn set_active_source_physical(&self, address: u16) -> Result<()> {
+        self.transmit(Cmd::active_source(self.registered_primary()?, address))
+    }
+
+    /// Ask `destination`, usually the TV, to start the audio return channel to this device
+    ///
+    /// The TV answers with `StartArc`, which is delivered like any other command.
+    ///
+    /// # Errors
+    ///
+    /// - PrimaryAddressUnregistered: this device has not claimed a logical address
+    /// - TransmitFailed: cec_sys::libcec_transmit fails
+    pub fn request_arc_start(&self, destination: LogicalAddress) -> Result<()> {
+        self.transmit(Cmd::request_arc_start(
+            self.registered_primary()?,
+            destination,
+        ))
+    }
+
+    /// Ask `destination` to end the audio return channel, see `request_arc_start`
+    ///
+    /// The TV answers with `EndArc`.
+    pub fn request_arc_end(&self, destination: LogicalAddress) -> Result<()> {
+        self.transmit(Cmd::request_arc_end(
+            self.registered_primary()?,
+            destination,
+        ))
+    }
+
+    pub fn get_active_source(&self) -> LogicalAddress {
+        let active_raw: cec_logical_address = unsafe { libcec_get_active_source(self.1) };
+        LogicalAddress::from_repr(active_raw).unwrap()
+    }
+
+    pub fn is_active_source(&self, address: LogicalAddress) -> Result<()> {
+        transmit_result(
+            unsafe { libcec_is_active_source(self.1, address.repr()) },
+            TransmitFailure::new("libcec_is_active_source").with_address(address),
+        )
+    }
+
+    pub fn get_device_power_status(&self, address: LogicalAddress) -> PowerStatus {
+        let status_raw: cec_power_status =
+            unsafe { libcec_get_device_power_status(self.1, address.repr()) };
+
+        PowerStatus::from_repr(status_raw).unwrap()
+    }
+
+    /// Vendor id reported by the device at `address`, 0 when unknown
+    ///
+    /// Compare with `VendorId` for the vendors libcec knows.
+    pub fn get_device_vendor_id(&self, address: LogicalAddress) -> u32 {
+        unsafe { libcec_get_device_vendor_id(self.1, address.repr()) }
+    }
+
+    /// Name the device at `address` shows on screen
+    ///
+    /// # Errors
+    ///
+    /// - TransmitFailed: cec_sys::libcec_get_device_osd_name fails
+    pub fn get_device_osd_name(&self, address: LogicalAddress) -> Result<String> {
+        let mut name: cec_osd_name = Default::default();
+        transmit_result(
+            unsafe { libcec_get_device_osd_name(self.1, address.repr(), name.as_mut_ptr()) },
+            TransmitFailure::new("libcec_get_device_osd_name").with_address(address),
+        )?;
+        Ok(from_c_chars(&name))
+    }
+
+    /// Physical address of the device at `address`, 0xFFFF when unknown
+    pub fn get_device_physical_address(&self, address: LogicalAddress) -> u16 {
+        unsafe { libcec_get_device_physical_address(self.1, address.repr()) }
+    }
+
+    /// CEC version the device at `address` implements
+    pub fn get_device_cec_version(&self, address: LogicalAddress) -> Version {
+        let version_raw = unsafe { libcec_get_device_cec_version(self.1, address.repr()) };
+        Version::from_repr(version_raw).unwrap_or(Version::VersionUnknown)
+    }
+
+    pub fn send_keypress(
+        &self,
+        address: LogicalAddress,
+        key: UserControlCode,
+        wait: bool,
+    ) -> Result<()> {
+        debug!("send_keypress: {key:?} -> {address:?}");
+        transmit_result(
+            unsafe { libcec_send_keypress(self.1, address.repr(), key.repr(), wait.into()) },
+            TransmitFailure::new("libcec_send_keypress")
+                .with_opcode(Opcode::UserControlPressed)
+                .with_address(address),
+        )
+    }
+
+    pub fn send_key_release(&self, address: LogicalAddress, wait: bool) -> Result<()> {
+        debug!("send_key_release: -> {address:?}");
+        transmit_result(
+            unsafe { libcec_send_key_release(self.1, address.repr(), wait.into()) },
+            TransmitFailure::new("libcec_send_key_release")
+                .with_opcode(Opcode::UserControlRelease)
+                .with_address(address),
+        )
+    }
+
+    /// Hold `key` down for `hold`, e.g. for a long press of power
+    ///
+    /// Sends the press, blocks the calling thread for `hold` and then sends the release. The
+    /// release is not sent when the press fails.
+    pub fn send_keypress_timed(
+        &self,
+        address: LogicalAddress,
+        key: UserControlCode,
+        hold: Duration,
+    ) -> Result<()> {
+        self.send_keypress(address, key, true)?;
+        thread::sleep(hold);
+        self.send_key_release(address, true)
+    }
+
+    pub fn volume_up(&self, send_release: bool) -> Result<()> {
+        transmit_result(
+            unsafe { libcec_volume_up(self.1, send_release.into()) },
+            TransmitFailure::new("libcec_volume_up"),
+        )
+    }
+
+    pub fn volume_down(&self, send_release: bool) -> Result<()> {
+        transmit_result(
+            unsafe { libcec_volume_down(self.1, send_release.into()) },
+            TransmitFailure::new("libcec_volume_down"),
+        )
+    }
+
+    pub fn mute_audio(&self, send_release: bool) -> Result<()> {
+        transmit_result(
+            unsafe { libcec_mute_audio(self.1, send_release.into()) },
+            TransmitFailure::new("libcec_mute_audio"),
+        )
+    }
+
+    /// Like `volume_up`, returning the audio status reported back by the audio system
+    ///
+    /// # Errors
+    ///
+    /// - TransmitFailed: no audio system reported its status, libcec's unknown volume
+    pub fn volume_up_status(&self, send_release: bool) -> Result<AudioState> {
+        audio_state_result(
+            unsafe { libcec_volume_up(self.1, send_release.into()) },
+            TransmitFailure::new("libcec_volume_up"),
+        )
+    }
+
+    /// Like `volume_down`, returning the resulting audio status, see `volume_up_status`
+    pub fn volume_down_status(&self, send_release: bool) -> Result<AudioState> {
+        audio_state_result(
+            unsafe { libcec_volume_down(self.1, send_release.into()) },
+            TransmitFailure::new("libcec_volume_down"),
+        )
+    }
+
+    /// Like `mute_audio`, returning the resulting audio status, see `volume_up_status`
+    pub fn mute_audio_status(&self, send_release: bool) -> Result<AudioState> {
+        audio_state_result(
+            unsafe { libcec_mute_audio(self.1, send_release.into()) },
+            TransmitFailure::new("libcec_mute_audio"),
+        )
+    }
+
+    pub fn audio_toggle_mute(&self) -> Result<()> {
+        transmit_result(
+            unsafe { libcec_audio_toggle_mute(self.1) },
+            TransmitFailure::new("libcec_audio_toggle_mute"),
+        )
+    }
+
+    pub fn audio_mute(&self) -> Result<()> {
+        transmit_result(
+            unsafe { libcec_audio_mute(self.1) },
+            TransmitFailure::new("libcec_audio_mute"),
+        )
+    }
+
+    pub fn audio_unmute(&self) -> Result<()> {
+        transmit_result(
+            unsafe { libcec_audio_unmute(self.1) },
+            TransmitFailure::new("libcec_audio_unmute"),
+        )
+    }
+
+    pub fn audio_get_status(&self) -> Result<()> {
+        transmit_result(
+            unsafe { libcec_audio_get_status(self.1) },
+            TransmitFailure::new("libcec_audio_get_status"),
+        )
+    }
+
+    pub fn set_inactive_view(&self) -> Result<()> {
+        transmit_result(
+            unsafe { libcec_set_inactive_view(self.1) },
+            TransmitFailure::new("libcec_set_inactive_view"),
+        )
+    }
+
+    pub fn set_logical_address(&self, address: LogicalAddress) -> Result<()> {
+        transmit_result(
+            unsafe { libcec_set_logical_address(self.1, address.repr()) },
+            TransmitFailure::new("libcec_set_logical_address").with_address(address),
+        )
+    }
+
+    pub fn switch_monitoring(&self, enable: bool) -> Result<()> {
+        transmit_result(
+            unsafe { libcec_switch_monitoring(self.1, enable.into()) },
+            TransmitFailure::new("libcec_switch_monitoring"),
+        )
+    }
+
+    pub fn set_deck_control_mode(&self, mode: DeckControlMode, send_update: bool) -> Result<()> {
+        transmit_result(
+            unsafe { libcec_set_deck_control_mode(self.1, mode.repr(), send_update.into()) },
+            TransmitFailure::new("libcec_set_deck_control_mode"),
+        )
+    }
+
+    pub fn set_deck_info(&self, info: DeckInfo, send_update: bool) -> Result<()> {
+        transmit_result(
+            unsafe { libcec_set_deck_info(self.1, info.repr(), send_update.into()) },
+            TransmitFailure::new("libcec_set_deck_info"),
+        )
+    }
+
+    pub fn set_menu_state(&self, state: MenuState, send_update: bool) -> Result<()> {
+        transmit_result(
+            unsafe { libcec_set_menu_state(self.1, state.repr(), send_update.into()) },
+            TransmitFailure::new("libcec_set_menu_state"),
+        )
+    }
+
+    /// Change the HDMI port of `base_device` that this device is connected to, e.g. after
+    /// moving the cable to another input
+    ///
+    /// # Errors
+    ///
+    /// - InvalidHdmiPort: `port` is outside of 1..=15
+    /// - TransmitFailed: cec_sys::libcec_set_hdmi_port fails
+    pub fn set_hdmi_port(&self, base_device: LogicalAddress, port: u8) -> Result<()> {
+        if !(1..=15).contains(&port) {
+            return Err(ConnectionError::InvalidHdmiPort(port).into());
+        }
+        transmit_result(
+            unsafe { libcec_set_hdmi_port(self.1, base_device.repr(), port) },
+            TransmitFailure::new("libcec_set_hdmi_port").with_address(base_device),
+        )
+    }
+
+    pub fn get_logical_addresses(&self) -> Result<LogicalAddresses> {
+        LogicalAddresses::try_from(unsafe { libcec_get_logical_addresses(self.1) })
+    }
+
+    /// Logical addresses of the devices libcec knows to be active on the bus, including
+    /// this one
+    pub fn get_active_devices(&self) -> Result<LogicalAddresses> {
+        LogicalAddresses::try_from(unsafe { libcec_get_active_devices(self.1) })
+    }
+
+    /// Poll the power status of `address` every `poll_interval` until it is `target`, e.g.
+    /// after `send_power_on_devices`
+    ///
+    /// The `InTransition*` states, and any other state than `target`, are waited out.
+    ///
+    /// # Errors
+    ///
+    /// - Timeout: `address` did not report `target` within `timeout`
+    pub fn wait_for_power_status(
+        &self,
+        address: LogicalAddress,
+        target: PowerStatus,
+        timeout: Duration,
+        poll_interval: Duration,
+    ) -> Result<PowerStatus> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let status = self.get_device_power_status(address);
+            if status == target {
+                return Ok(status);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(ConnectionError::Timeout.into());
+            }
+            thread::sleep(poll_interval.min(deadline - now));
+        }
+    }
+
+    /// Read back the configuration libcec is currently using, e.g. the negotiated
+    /// physical and logical addresses.
+    pub fn get_current_configuration(&self) -> Result<Configuration> {
+        let mut cfg: libcec_configuration;
+        unsafe {
+            cfg = std::mem::zeroed::<libcec_configuration>();
+            libcec_clear_configuration(&mut cfg);
+        }
+        if unsafe { libcec_get_current_configuration(self.1, &mut cfg) } == 0 {
+            return Err(ConnectionError::ConfigurationFailed.into());
+        }
+        Configuration::try_from(cfg)
+    }
+
+    /// Apply `cfg` to the open connection without reconnecting.
+    ///
+    /// libcec honors the device name and kind, the physical address (or HDMI port and base
+    /// device), wake and power off devices, `activate_source`, `power_off_on_standby`, the
+    /// language, the combo key and the button timings at runtime. Connection level settings,
+    /// i.e. `device`, `detect_device`, `timeout`, `keepalive`, `monitor_only` and
+    /// `adapter_type`, are ignored and not validated. Callbacks of `cfg` are ignored as well,
+    /// the callbacks of this connection are kept.
+    pub fn set_configuration(&self, cfg: &Cfg) -> Result<()> {
+        cfg.validate_applied()?;
+        let cfg = self.to_ffi_configuration(cfg);
+        if unsafe { libcec_set_configuration(self.1, &cfg) } == 0 {
+            Err(ConnectionError::ConfigurationFailed.into())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Release all logical addresses, e.g. to hand the bus over to another controller
+    ///
+    /// Applies the configuration this connection was opened with, with an unregistered
+    /// primary address and no other addresses, through the same path as `set_configuration`.
+    ///
+    /// libcec does not apply `logicalAddresses` from the configuration as is: it only
+    /// allocates addresses itself, from the device types, when it registers the client. When
+    /// `libcec_set_configuration` re-registers the client, e.g. because the primary device is
+    /// not powered on, addresses may be claimed again. Check `get_logical_addresses`
+    /// afterwards to see which addresses libcec actually holds.
+    ///
+    /// With `monitor_only`, libcec does not create a client for the connection. No addresses
+    /// are claimed to begin with, and `libcec_set_configuration` fails because there is no
+    /// client to configure.
+    ///
+    /// # Errors
+    ///
+    /// - ConfigurationFailed: cec_sys::libcec_set_configuration fails, e.g. in monitor mode
+    pub fn clear_logical_addresses(&self) -> Result<()> {
+        let cfg = self.cleared_address_configuration();
+        if unsafe { libcec_set_configuration(self.1, &cfg) } == 0 {
+            Err(ConnectionError::ConfigurationFailed.into())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Configuration applied by `clear_logical_addresses`
+    pub(crate) fn cleared_address_configuration(&self) -> libcec_configuration {
+        let mut cfg = self.to_ffi_configuration(&self.0);
+        cfg.logicalAddresses = LogicalAddresses::default().into();
+        cfg
+    }
+
+    /// Build information of libcec, e.g. version, git revision and compile flags
+    pub fn get_lib_info(&self) -> Result<String> {
+        let info = unsafe { libcec_get_lib_info(self.1) };
+        if info.is_null() {
+            return Err(ConnectionError::LibInfoUnavailable.into());
+        }
+        let info = unsafe { CStr::from_ptr(info) };
+        Ok(info.to_string_lossy().into_owned())
+    }
+
+    /// Whether the device at `address` is known to be active on the bus
+    pub fn is_active_device(&self, address: LogicalAddress) -> bool {
+        unsafe { libcec_is_active_device(self.1, address.repr()) != 0 }
+    }
+
+    /// Whether any active device on the bus is of the given `kind`
+    pub fn is_active_device_type(&self, kind: DeviceKind) -> bool {
+        unsafe { libcec_is_active_device_type(self.1, kind.repr()) != 0 }
+    }
+
+    /// Whether the adapter can store its configuration, e.g. in an EEPROM
+    pub fn can_persist_configuration(&self) -> bool {
+        unsafe { libcec_can_persist_configuration(self.1) != 0 }
+    }
+
+    /// Store `cfg` on the adapter so that it survives a power cycle
+    ///
+    /// Like `set_configuration`, connection level settings of `cfg` are ignored and not
+    /// validated.
+    ///
+    /// # Errors
+    ///
+    /// - PersistNotSupported: the adapter cannot persist its configuration
+    /// - ConfigurationFailed: cec_sys::libcec_persist_configuration fails
+    pub fn persist_configuration(&self, cfg: &Cfg) -> Result<()> {
+        if !self.can_persist_configuration() {
+            return Err(ConnectionError::PersistNotSupported.into());
+        }
+        cfg.validate_applied()?;
+        let mut cfg = self.to_ffi_configuration(cfg);
+        if unsafe { libcec_persist_configuration(self.1, &mut cfg) } == 0 {
+            Err(ConnectionError::ConfigurationFailed.into())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Initialise the host's video layer for use without a media center
+    ///
+    /// Only needed on platforms where libcec talks to the display driver directly, e.g. the
+    /// Raspberry Pi firmware adapter, when nothing else has initialised it. On other
+    /// platforms this is a no-op.
+    pub fn init_video_standalone(&self) {
+        unsafe { libcec_init_video_standalone(self.1) }
+    }
+
+    /// Convert `cfg` for passing to libcec, keeping the callbacks registered for this connection
+    fn to_ffi_configuration(&self, cfg: &Cfg) -> libcec_configuration {
+        let mut ffi_cfg: libcec_configuration = cfg.into();
+        ffi_cfg.callbacks = self.2.table_ptr();
+        ffi_cfg.callbackParam = self.2.user_data();
+        ffi_cfg
+    }
+
+    // Unimplemented:
+    // extern DECLSPEC int libcec_set_physical_address(libcec_connection_t connection, uint16_t iPhysicalAddress);
+    // extern DECLSPEC int libcec_set_osd_string(libcec_connection_t connection, CEC_NAMESPACE cec_logical_address iLogicalAddress, CEC_NAMESPACE cec_display_control duration, const char* strMessage);
+    // extern DECLSPEC int libcec_get_device_menu_language(libcec_connection_t connection, CEC_NAMESPACE cec_logical_address iLogicalAddress, CEC_NAMESPACE cec_menu_language language);
+    // extern DECLSPEC int libcec_set_stream_path_logical(libcec_connection_t connection, CEC_NAMESPACE cec_logical_address iAddress);
+    // extern DECLSPEC int libcec_set_stream_path_physical(libcec_connection_t connection, uint16_t iPhysicalAddress);
+    // extern DECLSPEC void libcec_rescan_devices(libcec_connection_t connection);
+    // extern DECLSPEC int libcec_is_libcec_active_source(libcec_connection_t connection);
+    // extern DECLSPEC uint16_t libcec_get_adapter_vendor_id(libcec_connection_t connection);
+    // extern DECLSPEC uint16_t libcec_get_adapter_product_id(libcec_connection_t connection);
+}
+
+impl Cfg {
+    /// Configuration with only the required fields set, the same as building
+    /// `CfgBuilder::default().name(name).kind(kind)`
+    ///
+    /// Every optional field is `None`, so libcec's defaults apply, and the open timeout is
+    /// 5 seconds. No `device` is set: use `with_device` or `with_detect_device` before
+    /// connecting. This is the way to create a configuration without the `builder` feature.
+    pub fn new(name: String, kind: DeviceKind) -> Cfg {
+        Cfg {
+            on_key_press: None,
+            on_raw_key_press: None,
+            on_command_received: None,
+            on_log_message: None,
+            on_cfg_changed: None,
+            on_alert: None,
+            on_menu_state_change: None,
+            on_source_activated: None,
+            on_event: None,
+            device: None,
+            detect_device: None,
+            timeout: Duration::from_secs(5),
+            reclaim_active_source_on_tv_wake: None,
+            keepalive: None,
+            name,
+            kind,
+            physical_address: None,
+            base_device: None,
+            hdmi_port: None,
+            tv_vendor: None,
+            wake_devices: None,
+            power_off_devices: None,
+            settings_from_rom: None,
+            activate_source: None,
+            power_off_on_standby: None,
+            language: None,
+            monitor_only: None,
+            adapter_type: None,
+            combo_key: None,
+            combo_key_timeout: None,
+            button_repeat_rate: None,
+            button_release_delay: None,
+            double_tap_timeout: None,
+            autowake_avr: None,
+        }
+    }
+
+    /// Open the adapter at `device`, e.g. `/dev/ttyACM0`
+    pub fn with_device(mut self, device: String) -> Cfg {
+        self.device = Some(device);
+        self
+    }
+
+    /// Open the first adapter libcec detects, see `CfgBuilder::detect_device`
+    pub fn with_detect_device(mut self) -> Cfg {
+        self.detect_device = Some(true);
+        self
+    }
+
+    /// Poll `target` every `interval` while connected, see `CfgBuilder::keepalive`
+    pub fn with_keepalive(mut self, interval: Duration, target: LogicalAddress) -> Cfg {
+        self.keepalive = Some(Keepalive { interval, target });
+        self
+    }
+
+    /// Open connection to configuration represented by this object
+    ///
+    ///
+    /// # Errors
+    ///
+    /// Error is returned in following cases
+    /// - LibInitFailed: cec_sys::libcec_initialise fails
+    /// - AdapterOpenFailed: cec_sys::libcec_open fails
+    /// - CallbackRegistrationFailed: cec_sys::libcec_enable_callbacks fails
+    /// - FfiError: `device`, or the port detected with `detect_device`, contains a NUL byte
+    /// - ZeroKeepaliveInterval: `keepalive` is set with a zero interval
+    /// - ThreadSpawnFailed: the `keepalive` thread, or the `transmit_async` worker used by
+    ///   `reclaim_active_source_on_tv_wake`, cannot be spawned
+    pub fn connect(mut self) -> Result<Connection> {
+        self.validate()?;
+        let mut cfg: libcec_configuration = (&self).into();
+        let pinned_callbacks = self.take_callbacks();
+        let detect_device = self.detect_device.unwrap_or(false);
+        let device = self.device.clone();
+        let timeout = self.timeout;
+        let open_timeout = duration_to_ms_u32(self.timeout);
+        let reclaim_active_source = self.reclaim_active_source_on_tv_wake.unwrap_or(false);
+        let kind = self.kind;
+        let adapter_type = self.adapter_type;
+        let keepalive = self.keepalive;
+
+        let handle = Handle(unsafe { libcec_initialise(&mut cfg) });
+        let mut connection = Connection(
+            self,
+            handle.0,
+            pinned_callbacks,
+            TransmitQueue::new(Box::new(move |command: &Cmd| {
+                transmit_command(handle.get(), command)
+            })),
+            None,
+        );
+
+        if connection.1.is_null() {
             return Err(ConnectionError::InitFailed.into());
         }
 
+        if reclaim_active_source {
+            // Claiming the active source blocks until libcec transmitted it, which must not
+            // hold up libcec's callback thread that observes the TV waking up
+            let caller = connection
+                .3
+                .caller()
+                .map_err(|e| ConnectionError::ThreadSpawnFailed(e.to_string()))?;
+            *connection.2.reclaim_active_source.lock().unwrap() =
+                Some(ActiveSourceReclaim::new(Box::new(move || {
+                    caller.call(Box::new(move || {
+                        if unsafe { libcec_set_active_source(handle.get(), kind.repr()) } == 0 {
+                            debug!("failed to reclaim the active source");
+                        }
+                    }))
+                })));
+        }
+
         let resolved_device = match detect_device {
-            true => match Self::detect_device(&connection) {
+            true => match Self::detect_device(timeout, adapter_type) {
                 Ok(x) => x,
                 Err(e) => return Err(e),
             },
             false => match device {
-                Some(x) => CString::new(x)?,
+                Some(x) => CString::new(x).map_err(ConnectionError::FfiError)?,
                 None => return Err(ConnectionError::DeviceMissing.into()),
             },
         };
@@ -605,48 +1815,370 @@ impl Cfg {
             return Err(ConnectionError::AdapterOpenFailed.into());
         }
 
-        let callback_ret = unsafe {
-            cec_sys::libcec_set_callbacks(
-                connection.1,
-                addr_of_mut!(CALLBACKS),
-                rust_callbacks_as_void_ptr,
-            )
-        };
-        if callback_ret == 0 {
-            return Err(ConnectionError::CallbackRegistrationFailed.into());
+        unsafe { callback::register_callbacks(connection.1, &connection.2)? };
+
+        if detect_device {
+            connection.0.device = Some(resolved_device.to_string_lossy().into_owned());
+        }
+
+        if let Some(keepalive) = keepalive {
+            connection.4 = Some(
+                KeepaliveThread::spawn(
+                    keepalive,
+                    Box::new(move |target| poll_device(handle.get(), target)),
+                )
+                .map_err(|e| ConnectionError::ThreadSpawnFailed(e.to_string()))?,
+            );
         }
 
         Ok(connection)
     }
 
-    fn detect_device(connection: &Connection) -> Result<CString> {
-        let mut devices: [cec_sys::cec_adapter_descriptor; 10] = unsafe { std::mem::zeroed() };
+    /// Consume self.*_callback and build the connection's `Callbacks` from those
+    pub(crate) fn take_callbacks(&mut self) -> Pin<Box<Callbacks>> {
+        Box::pin(Callbacks {
+            on_key_press: Mutex::new(self.on_key_press.take()),
+            on_raw_key_press: Mutex::new(self.on_raw_key_press.take()),
+            on_cmd_received: Mutex::new(self.on_command_received.take()),
+            on_log_msg: Mutex::new(self.on_log_message.take()),
+            on_cfg_changed: Mutex::new(self.on_cfg_changed.take()),
+            on_alert: Mutex::new(self.on_alert.take()),
+            on_menu_state_changed: Mutex::new(self.on_menu_state_change.take()),
+            on_source_activated: Mutex::new(self.on_source_activated.take()),
+            on_event: Mutex::new(self.on_event.take()),
+            reclaim_active_source: Mutex::new(None),
+            pending: Pending::default(),
+            cmd_waiters: Mutex::new(Vec::new()),
+            key_press_forwarders: Forwarders::default(),
+            cmd_forwarders: Forwarders::default(),
+            log_msg_forwarders: Forwarders::default(),
+            table: CALLBACK_TABLE,
+        })
+    }
+
+    /// Copy of this configuration without the callbacks, e.g. to use as a template for
+    /// several connections
+    ///
+    /// Callbacks are boxed closures and cannot be cloned, all of them are `None` in the copy.
+    pub fn clone_config(&self) -> Cfg {
+        Cfg {
+            on_key_press: None,
+            on_raw_key_press: None,
+            on_command_received: None,
+            on_log_message: None,
+            on_cfg_changed: None,
+            on_alert: None,
+            on_menu_state_change: None,
+            on_source_activated: None,
+            on_event: None,
+            device: self.device.clone(),
+            detect_device: self.detect_device,
+            timeout: self.timeout,
+            reclaim_active_source_on_tv_wake: self.reclaim_active_source_on_tv_wake,
+            keepalive: self.keepalive,
+            name: self.name.clone(),
+            kind: self.kind,
+            physical_address: self.physical_address,
+            base_device: self.base_device,
+            hdmi_port: self.hdmi_port,
+            tv_vendor: self.tv_vendor,
+            wake_devices: self.wake_devices.clone(),
+            power_off_devices: self.power_off_devices.clone(),
+            settings_from_rom: self.settings_from_rom,
+            activate_source: self.activate_source,
+            power_off_on_standby: self.power_off_on_standby,
+            language: self.language.clone(),
+            monitor_only: self.monitor_only,
+            adapter_type: self.adapter_type,
+            combo_key: self.combo_key,
+            combo_key_timeout: self.combo_key_timeout,
+            button_repeat_rate: self.button_repeat_rate,
+            button_release_delay: self.button_release_delay,
+            double_tap_timeout: self.double_tap_timeout,
+            autowake_avr: self.autowake_avr,
+        }
+    }
+
+    /// Names of the fields that are written to the `libcec_configuration` on connect, given
+    /// which of the optional fields are set. `name` and `kind` are always written.
+    pub fn applied_fields(&self) -> Vec<&'static str> {
+        let optional_fields = [
+            ("physical_address", self.physical_address.is_some()),
+            ("base_device", self.base_device.is_some()),
+            ("hdmi_port", self.hdmi_port.is_some()),
+            ("tv_vendor", self.tv_vendor.is_some()),
+            ("wake_devices", self.wake_devices.is_some()),
+            ("power_off_devices", self.power_off_devices.is_some()),
+            ("settings_from_rom", self.settings_from_rom.is_some()),
+            ("activate_source", self.activate_source.is_some()),
+            ("power_off_on_standby", self.power_off_on_standby.is_some()),
+            ("language", self.language.is_some()),
+            ("monitor_only", self.monitor_only.is_some()),
+            ("adapter_type", self.adapter_type.is_some()),
+            ("combo_key", self.combo_key.is_some()),
+            ("combo_key_timeout", self.combo_key_timeout.is_some()),
+            ("button_repeat_rate", self.button_repeat_rate.is_some()),
+            ("button_release_delay", self.button_release_delay.is_some()),
+            ("double_tap_timeout", self.double_tap_timeout.is_some()),
+            ("autowake_avr", self.autowake_avr.is_some()),
+        ];
+        let mut fields = vec!["name", "kind"];
+        fields.extend(
+            optional_fields
+                .into_iter()
+                .filter_map(|(field, is_set)| is_set.then_some(field)),
+        );
+        fields
+    }
+
+    /// Reject values that would otherwise be silently truncated or padded when copied into
+    /// the fixed size `libcec_configuration` arrays, an invalid `device`, and a zero
+    /// keepalive interval, which would poll the bus in a tight loop
+    pub(crate) fn validate(&self) -> Result<()> {
+        if self
+            .keepalive
+            .is_some_and(|keepalive| keepalive.interval.is_zero())
+        {
+            return Err(ConnectionError::ZeroKeepaliveInterval.into());
+        }
+        self.validate_applied()?;
+        if let Some(device) = &self.device {
+            CString::new(device.as_str()).map_err(ConnectionError::FfiError)?;
+            if let Some(address) = device.strip_prefix("tcp://")
+                && !address
+                    .rsplit_once(':')
+                    .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok())
+            {
+                return Err(ConnectionError::InvalidNetworkPort(device.clone()).into());
+            }
+        }
+        Ok(())
+    }
+
+    /// `validate` for the fields written to the `libcec_configuration` only, for applying
+    /// `self` to an open connection, which ignores the connection level settings
+    pub(crate) fn validate_applied(&self) -> Result<()> {
+        // libcec's name buffer is one byte longer, for the terminating NUL
+        if self.name.len() > OSD_NAME_MAX_LEN {
+            return Err(ConnectionError::DeviceNameTooLong(self.name.len()).into());
+        }
+        // libcec only uses the HDMI port and base device when the physical address is 0
+        if self.physical_address.is_some_and(|address| address.0 != 0)
+            && (self.hdmi_port.is_some() || self.base_device.is_some())
+        {
+            return Err(ConnectionError::ConflictingAddressConfig.into());
+        }
+        Ok(())
+    }
+
+    /// Probe the adapter on `port` and read its configuration without opening a connection
+    ///
+    /// # Errors
+    ///
+    /// - InitFailed: cec_sys::libcec_initialise fails
+    /// - DeviceInformationUnavailable: no adapter answered on `port` within `timeout`
+    /// - FfiError: `port` contains a NUL byte
+    pub fn device_information(port: &str, timeout: Duration) -> Result<Configuration> {
+        let port = CString::new(port).map_err(ConnectionError::FfiError)?;
+        let timeout = duration_to_ms_u32(timeout);
+        let cfg = with_unopened_connection(|connection| {
+            let mut cfg: libcec_configuration;
+            unsafe {
+                cfg = std::mem::zeroed::<libcec_configuration>();
+                libcec_clear_configuration(&mut cfg);
+            }
+            if unsafe {
+                libcec_get_device_information(connection, port.as_ptr(), &mut cfg, timeout)
+            } == 0
+            {
+                return Err(ConnectionError::DeviceInformationUnavailable.into());
+            }
+            Ok(cfg)
+        })?;
+        Configuration::try_from(cfg)
+    }
+
+    fn detect_device(timeout: Duration, adapter_type: Option<AdapterType>) -> Result<CString> {
+        let (sender, receiver) = mpsc::channel();
+        // libcec_detect_adapters cannot be cancelled and may block on a wedged adapter.
+        // Detect on a separate thread with its own libcec instance, so that giving up
+        // does not leave the thread using a connection we are about to destroy.
+        thread::spawn(move || {
+            let _ = sender.send(detect_adapters(None, true));
+        });
+        let adapters = receiver
+            .recv_timeout(timeout)
+            .map_err(|_| ConnectionError::NoAdapterFound)??;
+        let adapter = Self::select_adapter(adapters, adapter_type)?;
+        Ok(CString::new(adapter.com_name).map_err(ConnectionError::FfiError)?)
+    }
+
+    /// Pick the first adapter of `adapter_type` if given and present, otherwise the first one
+    pub(crate) fn select_adapter(
+        adapters: Vec<AdapterDescriptor>,
+        adapter_type: Option<AdapterType>,
+    ) -> Result<AdapterDescriptor> {
+        let preferred = adapter_type.and_then(|adapter_type| {
+            adapters
+                .iter()
+                .position(|adapter| adapter.adapter_type == adapter_type)
+        });
+        adapters
+            .into_iter()
+            .nth(preferred.unwrap_or(0))
+            .ok_or_else(|| ConnectionError::NoAdapterFound.into())
+    }
+}
+
+/// Major libcec version, i.e. the ABI, this crate was built against
+#[allow(clippy::unnecessary_cast)]
+pub const SUPPORTED_ABI: u32 = CEC_LIB_VERSION_MAJOR as u32;
+
+/// `(major, minor)` version of the libcec headers this crate was built against
+#[allow(clippy::unnecessary_cast)]
+pub fn libcec_version() -> (u8, u8) {
+    (CEC_LIB_VERSION_MAJOR as u8, CEC_LIB_VERSION_MINOR as u8)
+}
+
+/// Detect the CEC adapters connected to this machine without opening a connection
+///
+/// # Arguments
+///
+/// * `device_path` - only detect the adapter at this path, if given
+/// * `quick_scan` - skip querying the adapters for their firmware details
+///
+/// Adapters without a name are skipped.
+pub fn detect_adapters(
+    device_path: Option<&str>,
+    quick_scan: bool,
+) -> Result<Vec<AdapterDescriptor>> {
+    with_unopened_connection(|connection| detect_adapters_with(connection, device_path, quick_scan))
+}
+
+/// Run `f` with a libcec instance that is initialised but not connected to an adapter
+fn with_unopened_connection<T>(f: impl FnOnce(libcec_connection_t) -> Result<T>) -> Result<T> {
+    let mut cfg: libcec_configuration;
+    unsafe {
+        cfg = std::mem::zeroed::<libcec_configuration>();
+        libcec_clear_configuration(&mut cfg);
+    }
+    cfg.clientVersion = libcec_version::CURRENT as _;
+    let connection = unsafe { libcec_initialise(&mut cfg) };
+    if connection.is_null() {
+        return Err(ConnectionError::InitFailed.into());
+    }
+    let result = f(connection);
+    unsafe { libcec_destroy(connection) };
+    result
+}
+
+fn detect_adapters_with(
+    connection: libcec_connection_t,
+    device_path: Option<&str>,
+    quick_scan: bool,
+) -> Result<Vec<AdapterDescriptor>> {
+    const MAX_ADAPTERS: u8 = i8::MAX as u8;
+    let device_path = device_path
+        .map(CString::new)
+        .transpose()
+        .map_err(ConnectionError::FfiError)?;
+    // libcec reports at most as many adapters as fit the buffer, grow it until all fit
+    let mut capacity: u8 = 10;
+    let (devices, num_devices) = loop {
+        let mut devices: Vec<cec_adapter_descriptor> =
+            vec![unsafe { std::mem::zeroed() }; capacity as usize];
         let num_devices = unsafe {
-            cec_sys::libcec_detect_adapters(
-                connection.1,
-                &mut devices as _,
-                10,
-                std::ptr::null(),
-                true as i32,
+            libcec_detect_adapters(
+                connection,
+                devices.as_mut_ptr(),
+                capacity,
+                device_path
+                    .as_ref()
+                    .map_or(std::ptr::null(), |path| path.as_ptr()),
+                quick_scan.into(),
             )
         };
+        // The count is an int8_t, so more than i8::MAX adapters cannot be reported
+        if c_int::from(num_devices) < c_int::from(capacity) || capacity == MAX_ADAPTERS {
+            break (devices, num_devices);
+        }
+        capacity = capacity.saturating_mul(2).min(MAX_ADAPTERS);
+    };
 
-        if num_devices < 0 {
-            Err(ConnectionError::NoAdapterFound.into())
-        } else {
-            let device = devices[0]
-                .strComName
-                .into_iter()
-                .flat_map(u8::try_from)
-                .filter(|x| *x != 0)
-                .collect::<Vec<u8>>();
-            Ok(CString::new(device)?)
+    if num_devices < 0 {
+        return Err(ConnectionError::NoAdapterFound.into());
+    }
+    Ok(devices
+        .into_iter()
+        .take(num_devices as usize)
+        .map(AdapterDescriptor::from)
+        .filter(|adapter| !adapter.com_name.is_empty())
+        .collect())
+}
+
+impl<T> Default for Forwarders<T> {
+    fn default() -> Self {
+        Self(Mutex::new(Vec::new()))
+    }
+}
+
+impl<T: Clone + Send + 'static> Forwarders<T> {
+    fn subscribe(&self) -> mpsc::Receiver<T> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribe_with(Box::new(move |item: &T| sender.send(item.clone()).is_ok()));
+        receiver
+    }
+
+    pub(crate) fn subscribe_with(&self, forward: Box<Forward<T>>) {
+        self.0.lock().unwrap().push(forward);
+    }
+
+    /// Send `item` to every receiver, forgetting the ones that have been dropped
+    pub(crate) fn forward(&self, item: &T) {
+        if let Ok(mut senders) = self.0.lock() {
+            senders.retain(|forward| forward(item));
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.lock().map_or(true, |senders| senders.is_empty())
+    }
+}
+
+impl Callbacks {
+    /// Pointer passed to libcec as callback user data, see `Connection` for its invariant.
+    /// Every registration must use this, so that all of them agree on the same box.
+    pub(crate) fn user_data(&self) -> *mut c_void {
+        self as *const Self as *mut c_void
+    }
+
+    /// Pointer to this connection's callback table, registered alongside `user_data`
+    pub(crate) fn table_ptr(&self) -> *mut ICECCallbacks {
+        &self.table as *const _ as *mut _
+    }
+
+    /// Hand a received command to the `transmit_and_wait` calls waiting for it
+    pub(crate) fn notify_cmd_waiters(&self, command: &Cmd) {
+        if let Ok(mut waiters) = self.cmd_waiters.lock() {
+            waiters.retain(|waiter| {
+                if waiter.initiator == command.initiator && waiter.opcode == command.opcode {
+                    (waiter.send)(Ok(command.clone()));
+                    false
+                } else {
+                    true
+                }
+            });
         }
     }
 }
 
 impl Drop for Connection {
     fn drop(&mut self) {
+        // Fields drop after this, the threads must be joined before the handle is closed
+        if let Some(keepalive) = &mut self.4 {
+            keepalive.shutdown();
+        }
+        self.3.shutdown();
         unsafe {
             libcec_close(self.1);
             libcec_destroy(self.1);
@@ -654,6 +2186,146 @@ impl Drop for Connection {
     }
 }
 
+impl TransmitFailure {
+    pub fn new(call: &'static str) -> Self {
+        Self {
+            call,
+            opcode: None,
+            address: None,
+        }
+    }
+
+    pub fn with_opcode(mut self, opcode: Opcode) -> Self {
+        self.opcode = Some(opcode);
+        self
+    }
+
+    pub fn with_address(mut self, address: LogicalAddress) -> Self {
+        self.address = Some(address);
+        self
+    }
+}
+
+impl Display for TransmitFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.call)?;
+        if let Some(opcode) = self.opcode {
+            write!(f, ", opcode {opcode:?}")?;
+        }
+        if let Some(address) = self.address {
+            write!(f, ", address {address:?}")?;
+        }
+        Ok(())
+    }
+}
+
+impl AudioState {
+    /// Bit of the status byte that is set when the audio is muted
+    pub const MUTE_MASK: u8 = 0x80;
+    /// Bits of the status byte holding the volume
+    pub const VOLUME_MASK: u8 = 0x7F;
+    pub const VOLUME_MIN: u8 = 0x00;
+    pub const VOLUME_MAX: u8 = 0x64;
+    /// Volume reported when the audio system doesn't know it
+    pub const VOLUME_UNKNOWN: u8 = 0x7F;
+
+    /// Decode the status byte of a `ReportAudioStatus` command
+    pub fn from_raw(status: u8) -> Self {
+        Self {
+            muted: status & Self::MUTE_MASK != 0,
+            volume: status & Self::VOLUME_MASK,
+        }
+    }
+}
+
+impl PhysicalAddress {
+    /// Create a physical address from its four levels, `None` if any is above 0xF
+    pub fn from_nibbles(a: u8, b: u8, c: u8, d: u8) -> Option<Self> {
+        if [a, b, c, d].iter().any(|nibble| *nibble > 0xF) {
+            return None;
+        }
+        Some(Self(u16::from_be_bytes([(a << 4) | b, (c << 4) | d])))
+    }
+
+    /// The four levels of the physical address, most significant first
+    pub fn nibbles(&self) -> [u8; 4] {
+        let [high, low] = self.0.to_be_bytes();
+        [high >> 4, high & 0xF, low >> 4, low & 0xF]
+    }
+}
+
+impl Display for PhysicalAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d] = self.nibbles();
+        write!(f, "{a:x}.{b:x}.{c:x}.{d:x}")
+    }
+}
+
+impl MenuLanguage {
+    /// The language code, e.g. `eng`
+    pub fn as_str(&self) -> &str {
+        // Only ASCII letters are accepted on construction
+        str::from_utf8(&self.0).unwrap()
+    }
+}
+
+impl Display for MenuLanguage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for MenuLanguage {
+    type Err = ParseMenuLanguageError;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        match s.as_bytes() {
+            &[a, b, c] if [a, b, c].iter().all(u8::is_ascii_alphabetic) => Ok(Self([a, b, c])),
+            _ => Err(ParseMenuLanguageError::InvalidLanguage(s.to_owned())),
+        }
+    }
+}
+
+impl TryFrom<&str> for MenuLanguage {
+    type Error = ParseMenuLanguageError;
+
+    fn try_from(value: &str) -> result::Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl TryFrom<String> for MenuLanguage {
+    type Error = ParseMenuLanguageError;
+
+    fn try_from(value: String) -> result::Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl FromStr for PhysicalAddress {
+    type Err = ParsePhysicalAddressError;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        let mut nibbles = [0; 4];
+        let mut parts = s.split('.');
+        for nibble in &mut nibbles {
+            let part = parts
+                .next()
+                .ok_or(ParsePhysicalAddressError::InvalidFormat)?;
+            if part.len() != 1 {
+                return Err(ParsePhysicalAddressError::InvalidFormat);
+            }
+            *nibble = u8::from_str_radix(part, 16)
+                .map_err(|_| ParsePhysicalAddressError::InvalidFormat)?;
+        }
+        if parts.next().is_some() {
+            return Err(ParsePhysicalAddressError::InvalidFormat);
+        }
+        let [a, b, c, d] = nibbles;
+        Self::from_nibbles(a, b, c, d).ok_or(ParsePhysicalAddressError::InvalidFormat)
+    }
+}
+
 impl KnownLogicalAddress {
     pub fn new(address: LogicalAddress) -> Option<Self> {
         match address {
@@ -685,6 +2357,53 @@ impl Display for LogLevel {
     }
 }
 
+/// Lowercase `name` and drop separators, so that `VolumeUp`, `volume_up` and `volume-up`
+/// compare equal
+fn normalize_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| !matches!(c, '_' | '-' | ' '))
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Variant of `variants` whose `Debug` name matches `name`, ignoring case and separators
+fn find_by_name<T: Copy + fmt::Debug>(variants: &[T], name: &str) -> Option<T> {
+    let name = normalize_name(name);
+    variants
+        .iter()
+        .find(|variant| normalize_name(&format!("{variant:?}")) == name)
+        .copied()
+}
+
+/// Parses the variant name, e.g. `VolumeUp`, ignoring case, `_`, `-` and spaces. Also accepts
+/// the aliases `volup`, `voldown`, `ok`, `back` and `menu`.
+impl FromStr for UserControlCode {
+    type Err = ParseNameError;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        let alias = match normalize_name(s).as_str() {
+            "volup" => Some(UserControlCode::VolumeUp),
+            "voldown" => Some(UserControlCode::VolumeDown),
+            "ok" => Some(UserControlCode::Select),
+            "back" => Some(UserControlCode::Exit),
+            "menu" => Some(UserControlCode::RootMenu),
+            _ => None,
+        };
+        alias
+            .or_else(|| find_by_name(UserControlCode::all(), s))
+            .ok_or_else(|| ParseNameError::UnknownUserControlCode(s.to_owned()))
+    }
+}
+
+/// Parses the variant name, e.g. `GiveDevicePowerStatus`, ignoring case, `_`, `-` and spaces
+impl FromStr for Opcode {
+    type Err = ParseNameError;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        find_by_name(Opcode::all(), s).ok_or_else(|| ParseNameError::UnknownOpcode(s.to_owned()))
+    }
+}
+
 impl LogicalAddresses {
     pub fn with_only_primary(primary: &KnownLogicalAddress) -> LogicalAddresses {
         LogicalAddresses {
@@ -699,18 +2418,32 @@ impl LogicalAddresses {
     /// * `primary` - Primary address to use
     /// * `addresses` - other addresses to use. Primary is added to the set if not yet present
     ///
-    /// Returns `None` in the following cases
-    /// * when primary is `Unregistered` and `addresses` is non-empty
+    /// Returns `None` in the cases listed in `try_with_primary_and_addresses`
     ///
     pub fn with_primary_and_addresses(
         primary: &KnownLogicalAddress,
         addresses: &HashSet<RegisteredLogicalAddress>,
     ) -> Option<LogicalAddresses> {
+        Self::try_with_primary_and_addresses(primary, addresses).ok()
+    }
+
+    /// Like `with_primary_and_addresses`, but reports why the addresses are invalid
+    ///
+    /// # Errors
+    ///
+    /// - UnregisteredPrimaryWithAddresses: primary is `Unregistered` and `addresses` is
+    ///   non-empty
+    pub fn try_with_primary_and_addresses(
+        primary: &KnownLogicalAddress,
+        addresses: &HashSet<RegisteredLogicalAddress>,
+    ) -> std::result::Result<LogicalAddresses, TryFromLogicalAddressesError> {
         match (*primary).into() {
             // Invalid: Primary must be set if there are addresses
-            LogicalAddress::Unregistered if !addresses.is_empty() => None,
+            LogicalAddress::Unregistered if !addresses.is_empty() => {
+                Err(TryFromLogicalAddressesError::UnregisteredPrimaryWithAddresses)
+            }
             // Empty
-            LogicalAddress::Unregistered => Some(LogicalAddresses::default()),
+            LogicalAddress::Unregistered => Ok(LogicalAddresses::default()),
             // Non-empty
             _ => {
                 let mut cloned_addresses = addresses.clone();
@@ -718,13 +2451,138 @@ impl LogicalAddresses {
                 let registered_address: RegisteredLogicalAddress = (*primary).try_into().unwrap();
                 // We ensure that addresses always contains the primary
                 cloned_addresses.insert(registered_address);
-                Some(LogicalAddresses {
+                Ok(LogicalAddresses {
                     primary: *primary,
                     addresses: cloned_addresses,
                 })
             }
         }
     }
+
+    /// Create CecLogicalAddresses from primary address and any iterator of secondary addresses
+    ///
+    /// Returns `None` when any of `others` is `Unknown` or `Unregistered`, or in the cases
+    /// listed in `with_primary_and_addresses`
+    pub fn try_from_addresses(
+        primary: KnownLogicalAddress,
+        others: impl IntoIterator<Item = LogicalAddress>,
+    ) -> Option<LogicalAddresses> {
+        let addresses = others
+            .into_iter()
+            .map(RegisteredLogicalAddress::new)
+            .collect::<Option<HashSet<_>>>()?;
+        Self::with_primary_and_addresses(&primary, &addresses)
+    }
+}
+
+impl DataPacket {
+    /// Maximum number of bytes in a packet
+    pub const CAPACITY: usize = 64;
+
+    /// Number of bytes in the packet
+    ///
+    /// ```
+    /// use cec::{Cmd, LogicalAddress};
+    ///
+    /// let packet = Cmd::active_source(LogicalAddress::Playbackdevice1, 0x1000).parameters;
+    /// assert_eq!(packet.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Whether the packet holds no bytes
+    ///
+    /// ```
+    /// use cec::{Cmd, LogicalAddress};
+    ///
+    /// let packet = Cmd::image_view_on(LogicalAddress::Playbackdevice1, LogicalAddress::Tv).parameters;
+    /// assert!(packet.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Maximum number of bytes in a packet, always `CAPACITY`
+    ///
+    /// ```
+    /// use cec::{Cmd, DataPacket, LogicalAddress};
+    ///
+    /// let packet = Cmd::active_source(LogicalAddress::Playbackdevice1, 0x1000).parameters;
+    /// assert_eq!(packet.capacity(), DataPacket::CAPACITY);
+    /// ```
+    pub fn capacity(&self) -> usize {
+        self.0.capacity()
+    }
+
+    /// Number of bytes that can still be added
+    ///
+    /// ```
+    /// use cec::{Cmd, LogicalAddress};
+    ///
+    /// let packet = Cmd::active_source(LogicalAddress::Playbackdevice1, 0x1000).parameters;
+    /// assert_eq!(packet.remaining_capacity(), 62);
+    /// ```
+    pub fn remaining_capacity(&self) -> usize {
+        self.0.remaining_capacity()
+    }
+
+    /// The bytes of the packet
+    ///
+    /// ```
+    /// use cec::{Cmd, LogicalAddress};
+    ///
+    /// let packet = Cmd::active_source(LogicalAddress::Playbackdevice1, 0x1000).parameters;
+    /// assert_eq!(packet.as_slice(), &[0x10, 0x00]);
+    /// ```
+    pub fn as_slice(&self) -> &[u8] {
+        self.0.as_slice()
+    }
+
+    /// The bytes of the packet, mutably
+    ///
+    /// ```
+    /// use cec::{Cmd, LogicalAddress};
+    ///
+    /// let mut packet = Cmd::active_source(LogicalAddress::Playbackdevice1, 0x1000).parameters;
+    /// packet.as_mut_slice()[0] = 0x20;
+    /// assert_eq!(packet.as_slice(), &[0x20, 0x00]);
+    /// ```
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        self.0.as_mut_slice()
+    }
+}
+
+impl AsRef<[u8]> for DataPacket {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Deref for DataPacket {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl IntoIterator for DataPacket {
+    type Item = u8;
+    type IntoIter = arrayvec::IntoIter<u8, 64>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a DataPacket {
+    type Item = &'a u8;
+    type IntoIter = slice::Iter<'a, u8>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
 }
 
 impl DeviceKinds {
@@ -733,6 +2591,35 @@ impl DeviceKinds {
         inner.push(value);
         DeviceKinds(inner)
     }
+
+    /// Device kinds from `kinds`, libcec supports at most 5
+    ///
+    /// # Errors
+    ///
+    /// - Full: `kinds` has more than 5 elements, carries the first kind that did not fit
+    /// - Reserved: `kinds` contains `DeviceKind::Reserved`
+    pub fn from_slice(kinds: &[DeviceKind]) -> std::result::Result<Self, DeviceKindsError> {
+        let mut device_kinds = DeviceKinds(ArrayVec::new());
+        for kind in kinds {
+            device_kinds.push(*kind)?;
+        }
+        Ok(device_kinds)
+    }
+
+    /// Add `kind`, failing instead of panicking when all 5 slots are taken
+    ///
+    /// # Errors
+    ///
+    /// - Full: all 5 slots are taken
+    /// - Reserved: `kind` is `DeviceKind::Reserved`, which libcec reads as an empty slot
+    pub fn push(&mut self, kind: DeviceKind) -> std::result::Result<(), DeviceKindsError> {
+        if kind == DeviceKind::Reserved {
+            return Err(DeviceKindsError::Reserved);
+        }
+        self.0
+            .try_push(kind)
+            .map_err(|e| DeviceKindsError::Full(e.element()))
+    }
 }
 
 impl Default for LogicalAddresses {
@@ -744,6 +2631,85 @@ impl Default for LogicalAddresses {
     }
 }
 
+/// Transmit `command` on `connection`, shared by `transmit` and the `transmit_async` worker
+fn transmit_command(
+    connection: libcec_connection_t,
+    command: &Cmd,
+) -> result::Result<(), TransmitFailure> {
+    debug!(
+        "transmit: {:?} -> {:?}, opcode {:?}, parameters [{}]",
+        command.initiator,
+        command.destination,
+        command.opcode,
+        HexBytes(&command.parameters)
+    );
+    if unsafe { libcec_transmit(connection, &command.clone().into()) } == 0 {
+        Err(TransmitFailure::new("libcec_transmit")
+            .with_opcode(command.opcode)
+            .with_address(command.destination))
+    } else {
+        Ok(())
+    }
+}
+
+fn poll_device(
+    connection: libcec_connection_t,
+    address: LogicalAddress,
+) -> result::Result<(), TransmitFailure> {
+    trace!("poll_device: -> {address:?}");
+    if unsafe { libcec_poll_device(connection, address.repr()) } == 0 {
+        Err(TransmitFailure::new("libcec_poll_device").with_address(address))
+    } else {
+        Ok(())
+    }
+}
+
+/// Map the boolean result of a libcec call to `TransmitFailed`
+fn transmit_result(ret: c_int, failure: TransmitFailure) -> Result<()> {
+    if ret == 0 {
+        Err(ConnectionError::TransmitFailed(failure).into())
+    } else {
+        Ok(())
+    }
+}
+
+/// Space separated hex dump for log lines, cut off after `HexBytes::MAX_LEN` bytes
+struct HexBytes<'a>(&'a [u8]);
+
+impl HexBytes<'_> {
+    const MAX_LEN: usize = 16;
+}
+
+impl Display for HexBytes<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, byte) in self.0.iter().take(Self::MAX_LEN).enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{byte:02x}")?;
+        }
+        if self.0.len() > Self::MAX_LEN {
+            write!(f, " ... ({} bytes)", self.0.len())?;
+        }
+        Ok(())
+    }
+}
+
+fn audio_state_result(status: u8, failure: TransmitFailure) -> Result<AudioState> {
+    let state = AudioState::from(status);
+    if state.volume == AudioState::VOLUME_UNKNOWN {
+        Err(ConnectionError::TransmitFailed(failure).into())
+    } else {
+        Ok(state)
+    }
+}
+
+/// Milliseconds of `duration` for libcec's `uint32_t` millisecond fields, saturating at
+/// `u32::MAX` (about 49 days) instead of wrapping or panicking
+pub(crate) fn duration_to_ms_u32(duration: Duration) -> u32 {
+    duration.as_millis().try_into().unwrap_or(u32::MAX)
+}
+
 fn first_n<const N: usize>(string: &str) -> [::std::os::raw::c_char; N] {
     let mut data: [::std::os::raw::c_char; N] = [0; N];
     let bytes = string.as_bytes();
@@ -753,3 +2719,14 @@ fn first_n<const N: usize>(string: &str) -> [::std::os::raw::c_char; N] {
     }
     data
 }
+
+/// Decode a NUL-terminated C character array into an owned `String`
+fn from_c_chars(chars: &[::std::os::raw::c_char]) -> String {
+    let bytes = chars
+        .iter()
+        .take_while(|c| **c != 0)
+        // c_char is either u8 or i8. We use simple casting to convert u8 accordingly
+        .map(|c| *c as u8)
+        .collect::<Vec<u8>>();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
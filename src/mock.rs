@@ -0,0 +1,303 @@
+use std::{collections::HashMap, pin::Pin, sync::Mutex, time::Duration};
+
+use cec_sys::{cec_command, cec_keypress};
+
+use crate::{
+    duration_to_ms_u32, Callbacks, CecController, Cfg, Cmd, DeviceKind, KnownLogicalAddress,
+    LogicalAddress, LogicalAddresses, Opcode, PowerStatus, Result, UserControlCode,
+};
+
+/// In-memory stand-in for `Connection`, for testing code written against `CecController`
+///
+/// Every command is recorded instead of being sent. Helpers that libcec turns into commands
+/// are recorded as the command they send, e.g. `send_power_on_devices` as `ImageViewOn`.
+/// Commands and keypresses injected with `inject_*` are delivered to the callbacks of the
+/// `Cfg` the same way libcec delivers them. The active source follows `set_active_source`,
+/// `set_inactive_view` and injected `ActiveSource` commands.
+#[derive(Debug)]
+pub struct MockConnection {
+    addresses: LogicalAddresses,
+    physical_address: u16,
+    transmitted: Mutex<Vec<Cmd>>,
+    power_status: Mutex<HashMap<LogicalAddress, PowerStatus>>,
+    active_source: Mutex<LogicalAddress>,
+    callbacks: Pin<Box<Callbacks>>,
+}
+
+impl MockConnection {
+    /// Mock connection using the callbacks of `cfg`, claiming `primary` on the bus
+    pub fn new(mut cfg: Cfg, primary: KnownLogicalAddress) -> Self {
+        let callbacks = cfg.take_callbacks();
+        MockConnection {
+            addresses: LogicalAddresses::with_only_primary(&primary),
+            physical_address: cfg.physical_address.map_or(0, |address| address.0),
+            transmitted: Mutex::new(Vec::new()),
+            power_status: Mutex::new(HashMap::new()),
+            active_source: Mutex::new(LogicalAddress::Unknown),
+            callbacks,
+        }
+    }
+
+    /// Commands recorded so far, oldest first
+    pub fn transmitted(&self) -> Vec<Cmd> {
+        self.transmitted.lock().unwrap().clone()
+    }
+
+    /// Commands recorded so far, clearing the record
+    pub fn take_transmitted(&self) -> Vec<Cmd> {
+        std::mem::take(&mut *self.transmitted.lock().unwrap())
+    }
+
+    /// Power status returned by `get_device_power_status` for `address`, `Unknown` until set
+    pub fn set_power_status(&self, address: LogicalAddress, status: PowerStatus) {
+        self.power_status.lock().unwrap().insert(address, status);
+    }
+
+    /// Deliver `command` to the command callback, receivers and `on_event`
+    pub fn inject_command(&self, command: Cmd) {
+        if command.opcode == Opcode::ActiveSource {
+            *self.active_source.lock().unwrap() = command.initiator;
+        }
+        let command: cec_command = command.into();
+        if let Some(callback) = self.callbacks.table.commandReceived {
+            unsafe { callback(self.callbacks.user_data(), &command) };
+        }
+    }
+
+    /// Deliver a keypress to the keypress callbacks, receivers and `on_event`
+    pub fn inject_keypress(&self, key: UserControlCode, duration: Duration) {
+        let keypress = cec_keypress {
+            keycode: key.repr(),
+            duration: duration_to_ms_u32(duration),
+        };
+        if let Some(callback) = self.callbacks.table.keyPress {
+            unsafe { callback(self.callbacks.user_data(), &keypress) };
+        }
+    }
+
+    fn record(&self, command: Cmd) -> Result<()> {
+        self.transmitted.lock().unwrap().push(command);
+        Ok(())
+    }
+
+    fn primary(&self) -> LogicalAddress {
+        self.addresses.primary.into()
+    }
+
+    /// Press, and optionally release, `key` on the audio system
+    fn audio_key(&self, key: UserControlCode, send_release: bool) -> Result<()> {
+        self.record(Cmd::user_control_pressed(
+            self.primary(),
+            LogicalAddress::Audiosystem,
+            key,
+        ))?;
+        if send_release {
+            self.record(Cmd::user_control_release(
+                self.primary(),
+                LogicalAddress::Audiosystem,
+            ))?;
+        }
+        Ok(())
+    }
+}
+
+impl CecController for MockConnection {
+    fn transmit(&self, command: Cmd) -> Result<()> {
+        self.record(command)
+    }
+
+    fn send_keypress(
+        &self,
+        address: LogicalAddress,
+        key: UserControlCode,
+        _wait: bool,
+    ) -> Result<()> {
+        self.record(Cmd::user_control_pressed(self.primary(), address, key))
+    }
+
+    fn send_key_release(&self, address: LogicalAddress, _wait: bool) -> Result<()> {
+        self.record(Cmd::user_control_release(self.primary(), address))
+    }
+
+    fn send_power_on_devices(&self, address: LogicalAddress) -> Result<()> {
+        self.record(Cmd::image_view_on(self.primary(), address))
+    }
+
+    fn send_standby_devices(&self, address: LogicalAddress) -> Result<()> {
+        self.record(Cmd::standby(self.primary(), address))
+    }
+
+    fn set_active_source(&self, _device_type: DeviceKind) -> Result<()> {
+        *self.active_source.lock().unwrap() = self.primary();
+        self.record(Cmd::active_source(self.primary(), self.physical_address))
+    }
+
+    fn set_inactive_view(&self) -> Result<()> {
+        let mut active_source = self.active_source.lock().unwrap();
+        if *active_source == self.primary() {
+            *active_source = LogicalAddress::Unknown;
+        }
+        self.record(Cmd::try_new(
+            self.primary(),
+            LogicalAddress::Tv,
+            Opcode::InactiveSource,
+            &self.physical_address.to_be_bytes(),
+        )?)
+    }
+
+    fn get_active_source(&self) -> LogicalAddress {
+        *self.active_source.lock().unwrap()
+    }
+
+    fn get_device_power_status(&self, address: LogicalAddress) -> PowerStatus {
+        self.power_status
+            .lock()
+            .unwrap()
+            .get(&address)
+            .copied()
+            .unwrap_or(PowerStatus::Unknown)
+    }
+
+    fn get_logical_addresses(&self) -> Result<LogicalAddresses> {
+        Ok(self.addresses.clone())
+    }
+
+    fn volume_up(&self, send_release: bool) -> Result<()> {
+        self.audio_key(UserControlCode::VolumeUp, send_release)
+    }
+
+    fn volume_down(&self, send_release: bool) -> Result<()> {
+        self.audio_key(UserControlCode::VolumeDown, send_release)
+    }
+
+    fn mute_audio(&self, send_release: bool) -> Result<()> {
+        self.audio_key(UserControlCode::Mute, send_release)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{Keypress, Opcode};
+
+    fn mock(cfg: Cfg) -> MockConnection {
+        MockConnection::new(
+            cfg,
+            KnownLogicalAddress::new(LogicalAddress::Playbackdevice1).unwrap(),
+        )
+    }
+
+    fn cfg() -> Cfg {
+        Cfg::new("sample".into(), DeviceKind::PlaybackDevice)
+    }
+
+    #[test]
+    fn test_records_transmitted() {
+        let mock = mock(cfg());
+        let controller: &dyn CecController = &mock;
+        controller
+            .send_keypress(LogicalAddress::Tv, UserControlCode::Power, true)
+            .unwrap();
+        controller
+            .send_key_release(LogicalAddress::Tv, true)
+            .unwrap();
+        controller
+            .transmit(Cmd::standby(
+                LogicalAddress::Playbackdevice1,
+                LogicalAddress::BROADCAST,
+            ))
+            .unwrap();
+
+        let opcodes: Vec<_> = mock
+            .take_transmitted()
+            .into_iter()
+            .map(|command| command.opcode)
+            .collect();
+        assert_eq!(
+            opcodes,
+            vec![
+                Opcode::UserControlPressed,
+                Opcode::UserControlRelease,
+                Opcode::Standby
+            ]
+        );
+        assert!(mock.transmitted().is_empty());
+    }
+
+    #[test]
+    fn test_power_status() {
+        let mock = mock(cfg());
+        assert_eq!(
+            mock.get_device_power_status(LogicalAddress::Tv),
+            PowerStatus::Unknown
+        );
+        mock.set_power_status(LogicalAddress::Tv, PowerStatus::On);
+        assert_eq!(
+            mock.get_device_power_status(LogicalAddress::Tv),
+            PowerStatus::On
+        );
+    }
+
+    #[test]
+    fn test_active_source() {
+        let mock = mock(cfg());
+        // Generic code takes the bus by reference
+        fn claim(bus: impl CecController) -> LogicalAddress {
+            bus.set_active_source(DeviceKind::PlaybackDevice).unwrap();
+            bus.get_active_source()
+        }
+        assert_eq!(claim(&mock), LogicalAddress::Playbackdevice1);
+
+        mock.set_inactive_view().unwrap();
+        assert_eq!(mock.get_active_source(), LogicalAddress::Unknown);
+
+        mock.inject_command(Cmd::active_source(LogicalAddress::Tuner1, 0x2000));
+        assert_eq!(mock.get_active_source(), LogicalAddress::Tuner1);
+    }
+
+    #[test]
+    fn test_volume() {
+        let mock = mock(cfg());
+        mock.volume_up(true).unwrap();
+        mock.mute_audio(false).unwrap();
+        let commands = mock.take_transmitted();
+        assert_eq!(
+            commands
+                .iter()
+                .map(|command| command.to_bytes().to_vec())
+                .collect::<Vec<_>>(),
+            vec![
+                vec![0x45, 0x44, 0x41],
+                vec![0x45, 0x45],
+                vec![0x45, 0x44, 0x43]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_inject() {
+        let commands = Arc::new(Mutex::new(Vec::new()));
+        let keypresses = Arc::new(Mutex::new(Vec::new()));
+        let cfg = {
+            let commands = commands.clone();
+            let keypresses = keypresses.clone();
+            let mut cfg = cfg();
+            cfg.on_command_received = Some(Box::new(move |command: Cmd| {
+                commands.lock().unwrap().push(command.opcode)
+            }));
+            cfg.on_key_press = Some(Box::new(move |keypress: Keypress| {
+                keypresses.lock().unwrap().push(keypress.keycode)
+            }));
+            cfg
+        };
+        let mock = mock(cfg);
+
+        mock.inject_command(Cmd::standby(LogicalAddress::Tv, LogicalAddress::BROADCAST));
+        mock.inject_keypress(UserControlCode::Select, Duration::from_millis(100));
+
+        assert_eq!(*commands.lock().unwrap(), vec![Opcode::Standby]);
+        assert_eq!(*keypresses.lock().unwrap(), vec![UserControlCode::Select]);
+    }
+}
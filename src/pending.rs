@@ -0,0 +1,75 @@
+use std::{
+    sync::{Condvar, Mutex},
+    time::Duration,
+};
+
+/// Operations in progress on a connection, waited for by `Connection::flush`
+#[derive(Debug, Default)]
+pub(crate) struct Pending {
+    count: Mutex<usize>,
+    idle: Condvar,
+}
+
+/// Keeps an operation counted as pending until dropped
+pub(crate) struct PendingGuard<'a>(&'a Pending);
+
+impl Pending {
+    /// Count an operation as pending until the returned guard is dropped
+    pub(crate) fn start(&self) -> PendingGuard<'_> {
+        self.add();
+        PendingGuard(self)
+    }
+
+    /// Count an operation as pending until `finish` is called, for operations ending on
+    /// another thread
+    pub(crate) fn add(&self) {
+        *self.count.lock().unwrap() += 1;
+    }
+
+    pub(crate) fn finish(&self) {
+        *self.count.lock().unwrap() -= 1;
+        self.idle.notify_all();
+    }
+
+    /// Wait until no operation is pending, returning `false` if that takes longer than
+    /// `timeout`
+    pub(crate) fn wait(&self, timeout: Duration) -> bool {
+        let (_, result) = self
+            .idle
+            .wait_timeout_while(self.count.lock().unwrap(), timeout, |count| *count > 0)
+            .unwrap();
+        !result.timed_out()
+    }
+}
+
+impl Drop for PendingGuard<'_> {
+    fn drop(&mut self) {
+        self.0.finish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn test_idle() {
+        assert!(Pending::default().wait(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_waits_for_guards() {
+        let pending = Pending::default();
+        thread::scope(|scope| {
+            let guard = pending.start();
+            assert!(!pending.wait(Duration::from_millis(1)));
+            scope.spawn(move || {
+                thread::sleep(Duration::from_millis(20));
+                drop(guard);
+            });
+            assert!(pending.wait(Duration::from_secs(5)));
+        });
+    }
+}
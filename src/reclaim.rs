@@ -0,0 +1,150 @@
+use log::trace;
+
+use crate::{Cmd, LogicalAddress, Opcode, PowerStatus};
+
+/// Re-sends the active source grab when the TV is observed waking up
+#[derive(derive_more::Debug)]
+pub(crate) struct ActiveSourceReclaim {
+    /// Last observed TV power state, `None` until the TV reports it
+    tv_on: Option<bool>,
+    /// Claims the active source, called on libcec's callback thread so it must not block
+    #[debug(skip)]
+    reclaim: Box<dyn FnMut() + Send>,
+}
+
+impl ActiveSourceReclaim {
+    pub(crate) fn new(reclaim: Box<dyn FnMut() + Send>) -> Self {
+        Self {
+            tv_on: None,
+            reclaim,
+        }
+    }
+
+    /// Track the TV power state from received commands, re-claiming the active source
+    /// on standby -> on transitions
+    ///
+    /// Nothing is reclaimed while the state is unknown, e.g. when the first command seen
+    /// from the TV is already one it only sends while on.
+    pub(crate) fn on_cmd(&mut self, command: &Cmd) {
+        if command.initiator != LogicalAddress::Tv {
+            return;
+        }
+        let tv_on = match command.opcode {
+            Opcode::Standby => false,
+            // Only sent by a TV that is on, e.g. right after it was switched on
+            Opcode::RequestActiveSource | Opcode::RoutingChange | Opcode::ActiveSource => true,
+            _ => match command.as_power_status() {
+                Some(PowerStatus::On | PowerStatus::InTransitionStandbyToOn) => true,
+                Some(PowerStatus::Standby | PowerStatus::InTransitionOnToStandby) => false,
+                _ => return,
+            },
+        };
+        if tv_on && self.tv_on == Some(false) {
+            trace!("TV woke up, reclaiming active source");
+            (self.reclaim)();
+        }
+        self.tv_on = Some(tv_on);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use arrayvec::ArrayVec;
+
+    use super::*;
+    use crate::DataPacket;
+
+    fn cmd(initiator: LogicalAddress, opcode: Opcode, parameters: &[u8]) -> Cmd {
+        Cmd {
+            initiator,
+            destination: LogicalAddress::BROADCAST,
+            ack: false,
+            eom: true,
+            opcode,
+            parameters: DataPacket(ArrayVec::try_from(parameters).unwrap()),
+            opcode_set: true,
+            transmit_timeout: Duration::ZERO,
+        }
+    }
+
+    fn power_status(status: PowerStatus) -> Cmd {
+        cmd(
+            LogicalAddress::Tv,
+            Opcode::ReportPowerStatus,
+            &[status.repr() as u8],
+        )
+    }
+
+    /// Reclaim counting its calls
+    fn reclaim() -> (ActiveSourceReclaim, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let reclaim = ActiveSourceReclaim::new(Box::new(move || {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }));
+        (reclaim, calls)
+    }
+
+    #[test]
+    fn test_standby_to_on() {
+        let (mut reclaim, calls) = reclaim();
+        reclaim.on_cmd(&cmd(LogicalAddress::Tv, Opcode::Standby, &[]));
+        reclaim.on_cmd(&power_status(PowerStatus::On));
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+
+        reclaim.on_cmd(&power_status(PowerStatus::InTransitionOnToStandby));
+        reclaim.on_cmd(&power_status(PowerStatus::InTransitionStandbyToOn));
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn test_wake_opcodes() {
+        for opcode in [
+            Opcode::RequestActiveSource,
+            Opcode::RoutingChange,
+            Opcode::ActiveSource,
+        ] {
+            let (mut reclaim, calls) = reclaim();
+            reclaim.on_cmd(&power_status(PowerStatus::Standby));
+            reclaim.on_cmd(&cmd(LogicalAddress::Tv, opcode, &[0x10, 0x00]));
+            assert_eq!(calls.load(Ordering::Relaxed), 1, "{opcode:?}");
+        }
+    }
+
+    #[test]
+    fn test_on_to_on() {
+        let (mut reclaim, calls) = reclaim();
+        reclaim.on_cmd(&power_status(PowerStatus::Standby));
+        reclaim.on_cmd(&power_status(PowerStatus::On));
+        reclaim.on_cmd(&power_status(PowerStatus::On));
+        reclaim.on_cmd(&cmd(LogicalAddress::Tv, Opcode::RequestActiveSource, &[]));
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_unknown_state() {
+        let (mut reclaim, calls) = reclaim();
+        reclaim.on_cmd(&power_status(PowerStatus::On));
+        assert_eq!(calls.load(Ordering::Relaxed), 0);
+
+        // Unknown status and other initiators leave the state as is
+        reclaim.on_cmd(&power_status(PowerStatus::Standby));
+        reclaim.on_cmd(&power_status(PowerStatus::Unknown));
+        reclaim.on_cmd(&cmd(
+            LogicalAddress::Playbackdevice1,
+            Opcode::ActiveSource,
+            &[0x10, 0x00],
+        ));
+        assert_eq!(calls.load(Ordering::Relaxed), 0);
+        reclaim.on_cmd(&power_status(PowerStatus::On));
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
+}
@@ -0,0 +1,488 @@
+use std::collections::HashSet;
+
+use arrayvec::ArrayVec;
+
+use crate::types::from_u8;
+use crate::{
+    AnalogueBroadcastType, BroadcastSystem, Cmd, DataPacket, Error, ExternalSourceSpecifier,
+    LogicalAddress, Opcode, RecordingSequence, Result, TimerClearedStatusData, TimerError,
+};
+
+/// Length of the date, time and recurrence operands shared by all timer messages
+const TIMER_INFO_LEN: usize = 7;
+
+/// Date, start time, duration and recurrence of a timer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimerInfo {
+    /// Day of month, 1..=31.
+    pub day_of_month: u8,
+    /// Month of year, 1..=12.
+    pub month_of_year: u8,
+    /// Start hour, 0..=23.
+    pub start_hour: u8,
+    /// Start minute, 0..=59.
+    pub start_minute: u8,
+    /// Duration hours, 0..=99.
+    pub duration_hours: u8,
+    /// Duration minutes, 0..=59.
+    pub duration_minutes: u8,
+    /// Days of week on which the timer repeats, or only `OnceOnly`.
+    pub recording_sequence: HashSet<RecordingSequence>,
+}
+
+/// Source to record with a timer
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TimerSource {
+    /// Analogue service, sent with `SetAnalogueTimer`.
+    Analogue {
+        broadcast_type: AnalogueBroadcastType,
+        /// Frequency in units of 62.5 kHz.
+        frequency: u16,
+        broadcast_system: BroadcastSystem,
+    },
+    /// Digital service identification, sent with `SetDigitalTimer`.
+    Digital([u8; 7]),
+    /// External plug, sent with `SetExternalTimer`.
+    ExternalPlug(u8),
+    /// External physical address, sent with `SetExternalTimer`.
+    ExternalPhysicalAddress(u16),
+}
+
+/// Payload of the `SetAnalogueTimer`, `SetDigitalTimer` and `SetExternalTimer` messages
+/// (and their `Clear*Timer` counterparts)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Timer {
+    pub info: TimerInfo,
+    pub source: TimerSource,
+}
+
+impl Timer {
+    /// The opcode used for setting this timer
+    pub fn opcode(&self) -> Opcode {
+        match self.source {
+            TimerSource::Analogue { .. } => Opcode::SetAnalogueTimer,
+            TimerSource::Digital(_) => Opcode::SetDigitalTimer,
+            TimerSource::ExternalPlug(_) | TimerSource::ExternalPhysicalAddress(_) => {
+                Opcode::SetExternalTimer
+            }
+        }
+    }
+
+    /// The opcode used for clearing this timer
+    pub fn clear_opcode(&self) -> Opcode {
+        match self.source {
+            TimerSource::Analogue { .. } => Opcode::ClearAnalogueTimer,
+            TimerSource::Digital(_) => Opcode::ClearDigitalTimer,
+            TimerSource::ExternalPlug(_) | TimerSource::ExternalPhysicalAddress(_) => {
+                Opcode::ClearExternalTimer
+            }
+        }
+    }
+
+    /// Encode the timer into message parameters
+    ///
+    /// # Errors
+    ///
+    /// Error is returned when any of the fields is out of its CEC range
+    pub fn to_parameters(&self) -> Result<DataPacket> {
+        let mut data = ArrayVec::new();
+        data.extend(self.info.to_bytes()?);
+        match self.source {
+            TimerSource::Analogue {
+                broadcast_type,
+                frequency,
+                broadcast_system,
+            } => {
+                data.push(broadcast_type.repr() as u8);
+                data.extend(frequency.to_be_bytes());
+                data.push(broadcast_system.repr() as u8);
+            }
+            TimerSource::Digital(service) => data.extend(service),
+            TimerSource::ExternalPlug(plug) => {
+                data.push(ExternalSourceSpecifier::Plug.repr() as u8);
+                data.push(plug);
+            }
+            TimerSource::ExternalPhysicalAddress(address) => {
+                data.push(ExternalSourceSpecifier::PhysicalAddress.repr() as u8);
+                data.extend(address.to_be_bytes());
+            }
+        }
+        Ok(DataPacket(data))
+    }
+}
+
+impl Cmd {
+    /// `SetAnalogueTimer`, `SetDigitalTimer` or `SetExternalTimer` command programming
+    /// `timer` on `destination`
+    ///
+    /// # Errors
+    ///
+    /// Error is returned when any of the timer fields is out of its CEC range
+    pub fn set_timer(
+        initiator: LogicalAddress,
+        destination: LogicalAddress,
+        timer: &Timer,
+    ) -> Result<Self> {
+        Self::try_new(
+            initiator,
+            destination,
+            timer.opcode(),
+            &timer.to_parameters()?,
+        )
+    }
+
+    /// `ClearAnalogueTimer`, `ClearDigitalTimer` or `ClearExternalTimer` command removing
+    /// `timer` from `destination`
+    ///
+    /// # Errors
+    ///
+    /// Error is returned when any of the timer fields is out of its CEC range
+    pub fn clear_timer(
+        initiator: LogicalAddress,
+        destination: LogicalAddress,
+        timer: &Timer,
+    ) -> Result<Self> {
+        Self::try_new(
+            initiator,
+            destination,
+            timer.clear_opcode(),
+            &timer.to_parameters()?,
+        )
+    }
+
+    /// `SetDigitalTimer` command recording the digital service identified by `service`
+    ///
+    /// # Errors
+    ///
+    /// Error is returned when any of the `info` fields is out of its CEC range
+    pub fn set_digital_timer(
+        initiator: LogicalAddress,
+        destination: LogicalAddress,
+        info: TimerInfo,
+        service: [u8; 7],
+    ) -> Result<Self> {
+        let timer = Timer {
+            info,
+            source: TimerSource::Digital(service),
+        };
+        Self::set_timer(initiator, destination, &timer)
+    }
+
+    /// Result reported by a `TimerClearedStatus` command
+    ///
+    /// Returns `None` when the opcode is not `TimerClearedStatus`, the packet does not carry
+    /// exactly one operand or the status is unknown.
+    pub fn as_timer_cleared_status(&self) -> Option<TimerClearedStatusData> {
+        if self.opcode != Opcode::TimerClearedStatus {
+            return None;
+        }
+        let [status] = self.parameters.0.as_slice() else {
+            return None;
+        };
+        from_u8(*status)
+    }
+}
+
+impl TryFrom<&Cmd> for Timer {
+    type Error = Error;
+
+    fn try_from(command: &Cmd) -> Result<Self> {
+        let data = command.parameters.0.as_slice();
+        if data.len() < TIMER_INFO_LEN {
+            return Err(TimerError::InvalidLength.into());
+        }
+        let (info, rest) = data.split_at(TIMER_INFO_LEN);
+        let info = TimerInfo::from_bytes(info)?;
+        let source = match command.opcode {
+            Opcode::SetAnalogueTimer | Opcode::ClearAnalogueTimer => match *rest {
+                [broadcast_type, frequency_hi, frequency_lo, broadcast_system] => {
+                    TimerSource::Analogue {
+                        broadcast_type: from_u8(broadcast_type)
+                            .ok_or(TimerError::UnknownAnalogueBroadcastType)?,
+                        frequency: u16::from_be_bytes([frequency_hi, frequency_lo]),
+                        broadcast_system: from_u8(broadcast_system)
+                            .ok_or(TimerError::UnknownBroadcastSystem)?,
+                    }
+                }
+                _ => return Err(TimerError::InvalidLength.into()),
+            },
+            Opcode::SetDigitalTimer | Opcode::ClearDigitalTimer => {
+                TimerSource::Digital(rest.try_into().map_err(|_| TimerError::InvalidLength)?)
+            }
+            Opcode::SetExternalTimer | Opcode::ClearExternalTimer => match *rest {
+                [specifier, plug] if specifier == ExternalSourceSpecifier::Plug.repr() as u8 => {
+                    TimerSource::ExternalPlug(plug)
+                }
+                [specifier, address_hi, address_lo]
+                    if specifier == ExternalSourceSpecifier::PhysicalAddress.repr() as u8 =>
+                {
+                    TimerSource::ExternalPhysicalAddress(u16::from_be_bytes([
+                        address_hi, address_lo,
+                    ]))
+                }
+                [specifier, ..]
+                    if specifier != ExternalSourceSpecifier::Plug.repr() as u8
+                        && specifier != ExternalSourceSpecifier::PhysicalAddress.repr() as u8 =>
+                {
+                    return Err(TimerError::UnknownExternalSourceSpecifier.into())
+                }
+                _ => return Err(TimerError::InvalidLength.into()),
+            },
+            _ => return Err(TimerError::UnexpectedOpcode.into()),
+        };
+        Ok(Self { info, source })
+    }
+}
+
+impl TimerInfo {
+    pub(crate) fn to_bytes(&self) -> Result<[u8; TIMER_INFO_LEN]> {
+        if !(1..=31).contains(&self.day_of_month) || !(1..=12).contains(&self.month_of_year) {
+            return Err(TimerError::InvalidDate.into());
+        }
+        if self.start_hour > 23 || self.start_minute > 59 {
+            return Err(TimerError::InvalidTime.into());
+        }
+        if self.duration_hours > 99 || self.duration_minutes > 59 {
+            return Err(TimerError::InvalidDuration.into());
+        }
+        Ok([
+            self.day_of_month,
+            self.month_of_year,
+            to_bcd(self.start_hour),
+            to_bcd(self.start_minute),
+            to_bcd(self.duration_hours),
+            to_bcd(self.duration_minutes),
+            recording_sequence_to_byte(&self.recording_sequence)?,
+        ])
+    }
+
+    pub(crate) fn from_bytes(data: &[u8]) -> Result<Self> {
+        let [day_of_month, month_of_year, start_hour, start_minute, duration_hours, duration_minutes, recording_sequence] =
+            *data
+        else {
+            return Err(TimerError::InvalidLength.into());
+        };
+        let info = Self {
+            day_of_month,
+            month_of_year,
+            start_hour: from_bcd(start_hour).ok_or(TimerError::InvalidTime)?,
+            start_minute: from_bcd(start_minute).ok_or(TimerError::InvalidTime)?,
+            duration_hours: from_bcd(duration_hours).ok_or(TimerError::InvalidDuration)?,
+            duration_minutes: from_bcd(duration_minutes).ok_or(TimerError::InvalidDuration)?,
+            recording_sequence: recording_sequence_from_byte(recording_sequence)?,
+        };
+        // Validate ranges the same way as when encoding
+        info.to_bytes()?;
+        Ok(info)
+    }
+}
+
+const WEEKDAYS: [RecordingSequence; 7] = [
+    RecordingSequence::Sunday,
+    RecordingSequence::Monday,
+    RecordingSequence::Tuesday,
+    RecordingSequence::Wednesday,
+    RecordingSequence::Thursday,
+    RecordingSequence::Friday,
+    RecordingSequence::Saturday,
+];
+
+fn recording_sequence_to_byte(sequence: &HashSet<RecordingSequence>) -> Result<u8> {
+    let once_only = sequence.contains(&RecordingSequence::OnceOnly);
+    match (once_only, sequence.len()) {
+        // Either once only or some days of week, not both and not neither
+        (true, 1) => Ok(RecordingSequence::OnceOnly.repr() as u8),
+        (false, n) if n > 0 => Ok(sequence.iter().fold(0, |mask, day| mask | day.repr() as u8)),
+        _ => Err(TimerError::InvalidRecordingSequence.into()),
+    }
+}
+
+fn recording_sequence_from_byte(mask: u8) -> Result<HashSet<RecordingSequence>> {
+    // Bit 7 is reserved
+    if mask & 0x80 != 0 {
+        return Err(TimerError::InvalidRecordingSequence.into());
+    }
+    if mask == RecordingSequence::OnceOnly.repr() as u8 {
+        return Ok(HashSet::from([RecordingSequence::OnceOnly]));
+    }
+    Ok(WEEKDAYS
+        .into_iter()
+        .filter(|day| mask & day.repr() as u8 != 0)
+        .collect())
+}
+
+/// Encode a value 0..=99 as two BCD digits
+pub(crate) fn to_bcd(value: u8) -> u8 {
+    ((value / 10) << 4) | (value % 10)
+}
+
+/// Decode two BCD digits, `None` if either digit is not a decimal digit
+pub(crate) fn from_bcd(byte: u8) -> Option<u8> {
+    let (tens, ones) = (byte >> 4, byte & 0x0F);
+    if tens > 9 || ones > 9 {
+        None
+    } else {
+        Some(tens * 10 + ones)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::LogicalAddress;
+
+    fn weekly_timer() -> Timer {
+        Timer {
+            info: TimerInfo {
+                day_of_month: 14,
+                month_of_year: 2,
+                start_hour: 20,
+                start_minute: 30,
+                duration_hours: 1,
+                duration_minutes: 45,
+                recording_sequence: HashSet::from([
+                    RecordingSequence::Monday,
+                    RecordingSequence::Wednesday,
+                ]),
+            },
+            source: TimerSource::Analogue {
+                broadcast_type: AnalogueBroadcastType::Cable,
+                frequency: 0x1234,
+                broadcast_system: BroadcastSystem::PalBG,
+            },
+        }
+    }
+
+    fn cmd(opcode: Opcode, parameters: &[u8]) -> Cmd {
+        Cmd {
+            initiator: LogicalAddress::Tv,
+            destination: LogicalAddress::Recordingdevice1,
+            ack: false,
+            eom: true,
+            opcode,
+            parameters: DataPacket(ArrayVec::try_from(parameters).unwrap()),
+            opcode_set: true,
+            transmit_timeout: Duration::from_secs(1),
+        }
+    }
+
+    #[test]
+    fn test_weekly_timer_to_parameters() {
+        let timer = weekly_timer();
+        assert_eq!(timer.opcode(), Opcode::SetAnalogueTimer);
+        assert_eq!(
+            timer.to_parameters().unwrap().0.as_slice(),
+            &[14, 2, 0x20, 0x30, 0x01, 0x45, 0x0A, 0x00, 0x12, 0x34, 0x00]
+        );
+    }
+
+    #[test]
+    fn test_weekly_timer_round_trip() {
+        let timer = weekly_timer();
+        let command = cmd(timer.opcode(), &timer.to_parameters().unwrap().0);
+        assert_eq!(Timer::try_from(&command), Ok(timer));
+    }
+
+    #[test]
+    fn test_external_timer_round_trip() {
+        let mut timer = weekly_timer();
+        timer.info.recording_sequence = HashSet::from([RecordingSequence::OnceOnly]);
+        timer.source = TimerSource::ExternalPhysicalAddress(0x2100);
+        let parameters = timer.to_parameters().unwrap();
+        assert_eq!(&parameters.0[6..], &[0x00, 0x05, 0x21, 0x00]);
+        let command = cmd(Opcode::SetExternalTimer, &parameters.0);
+        assert_eq!(Timer::try_from(&command), Ok(timer));
+    }
+
+    #[test]
+    fn test_set_digital_timer() {
+        let mut info = weekly_timer().info;
+        info.recording_sequence = HashSet::from([RecordingSequence::OnceOnly]);
+        let command = Cmd::set_digital_timer(
+            LogicalAddress::Tv,
+            LogicalAddress::Recordingdevice1,
+            info,
+            [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07],
+        )
+        .unwrap();
+        assert_eq!(
+            command.to_bytes().as_slice(),
+            &[
+                0x01, 0x97, 14, 2, 0x20, 0x30, 0x01, 0x45, 0x00, 0x01, 0x02, 0x03, 0x04, 0x05,
+                0x06, 0x07
+            ]
+        );
+    }
+
+    #[test]
+    fn test_clear_timer() {
+        let command = Cmd::clear_timer(
+            LogicalAddress::Tv,
+            LogicalAddress::Recordingdevice1,
+            &weekly_timer(),
+        )
+        .unwrap();
+        assert_eq!(command.opcode, Opcode::ClearAnalogueTimer);
+        assert_eq!(Timer::try_from(&command), Ok(weekly_timer()));
+    }
+
+    #[test]
+    fn test_timer_cleared_status() {
+        assert_eq!(
+            cmd(Opcode::TimerClearedStatus, &[0x80]).as_timer_cleared_status(),
+            Some(TimerClearedStatusData::Cleared)
+        );
+        assert_eq!(
+            cmd(Opcode::TimerClearedStatus, &[0x01]).as_timer_cleared_status(),
+            Some(TimerClearedStatusData::NotClearedNoMatching)
+        );
+        assert_eq!(
+            cmd(Opcode::TimerClearedStatus, &[0x42]).as_timer_cleared_status(),
+            None
+        );
+        assert_eq!(
+            cmd(Opcode::TimerStatus, &[0x80]).as_timer_cleared_status(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_invalid_timers() {
+        let mut timer = weekly_timer();
+        timer.info.start_minute = 60;
+        assert_eq!(timer.to_parameters(), Err(TimerError::InvalidTime.into()));
+
+        let mut timer = weekly_timer();
+        timer
+            .info
+            .recording_sequence
+            .insert(RecordingSequence::OnceOnly);
+        assert_eq!(
+            timer.to_parameters(),
+            Err(TimerError::InvalidRecordingSequence.into())
+        );
+
+        let truncated = cmd(Opcode::SetAnalogueTimer, &[14, 2, 0x20, 0x30, 0x01, 0x45]);
+        assert_eq!(
+            Timer::try_from(&truncated),
+            Err(TimerError::InvalidLength.into())
+        );
+
+        let bad_bcd = cmd(
+            Opcode::SetDigitalTimer,
+            &[14, 2, 0x2A, 0x30, 0x01, 0x45, 0x00, 0, 0, 0, 0, 0, 0, 0],
+        );
+        assert_eq!(
+            Timer::try_from(&bad_bcd),
+            Err(TimerError::InvalidTime.into())
+        );
+
+        let wrong_opcode = cmd(Opcode::Standby, &[14, 2, 0x20, 0x30, 0x01, 0x45, 0x00]);
+        assert_eq!(
+            Timer::try_from(&wrong_opcode),
+            Err(TimerError::UnexpectedOpcode.into())
+        );
+    }
+}
@@ -0,0 +1,353 @@
+use std::{
+    io, result,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc, Arc, OnceLock,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use cec_sys::libcec_connection_t;
+use log::debug;
+
+use crate::{pending::Pending, Cmd, Connection, Forwarders, TransmitFailure};
+
+/// Identifies a command queued with `Connection::transmit_async`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TransmitId(pub u64);
+
+/// Outcome of a command queued with `Connection::transmit_async`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransmitCompletion {
+    pub id: TransmitId,
+    pub result: result::Result<(), TransmitFailure>,
+}
+
+/// Transmits one command, called on the worker thread
+pub(crate) type TransmitFn = dyn Fn(&Cmd) -> result::Result<(), TransmitFailure> + Send + Sync;
+
+/// A libcec call handed to the worker thread, see `Caller`
+pub(crate) type CallFn = dyn FnOnce() + Send;
+
+/// The libcec handle, moved to the worker thread
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct Handle(pub(crate) libcec_connection_t);
+// SAFETY: sharing the handle between threads is justified on `Connection`'s `Sync` impl. The
+// threads using a `Handle` are joined before the handle is closed.
+unsafe impl Send for Handle {}
+unsafe impl Sync for Handle {}
+
+impl Handle {
+    /// Closures must call this rather than read `.0`, which would capture only the raw
+    /// pointer and lose `Send`
+    pub(crate) fn get(self) -> libcec_connection_t {
+        self.0
+    }
+}
+
+/// Commands waiting for the worker thread of `transmit_async`
+///
+/// The worker is spawned on the first queued command. Dropping the queue waits for it to
+/// transmit everything already queued, so it must be dropped before the handle is closed.
+#[derive(derive_more::Debug)]
+pub(crate) struct TransmitQueue {
+    #[debug(skip)]
+    transmit: Arc<TransmitFn>,
+    next_id: AtomicU64,
+    #[debug(skip)]
+    worker: OnceLock<Worker>,
+    #[debug(skip)]
+    completion_forwarders: Arc<Forwarders<TransmitCompletion>>,
+    /// Commands handed to the worker whose completion was not forwarded yet
+    pending: Arc<Pending>,
+}
+
+struct Worker {
+    sender: mpsc::Sender<Job>,
+    thread: JoinHandle<()>,
+}
+
+enum Job {
+    Transmit(TransmitId, Cmd),
+    Call(Box<CallFn>),
+    /// Sent by `shutdown`, `Caller`s keep the channel open
+    Stop,
+}
+
+/// Hands libcec calls to the worker thread of a `TransmitQueue`, for code that cannot reach
+/// the queue and must not call libcec itself, e.g. libcec's callback thread
+#[derive(Debug)]
+pub(crate) struct Caller {
+    sender: mpsc::Sender<Job>,
+    pending: Arc<Pending>,
+}
+
+impl TransmitQueue {
+    pub(crate) fn new(transmit: Box<TransmitFn>) -> Self {
+        Self {
+            transmit: Arc::from(transmit),
+            next_id: AtomicU64::new(0),
+            worker: OnceLock::new(),
+            completion_forwarders: Arc::default(),
+            pending: Arc::default(),
+        }
+    }
+
+    pub(crate) fn push(&self, command: Cmd) -> TransmitId {
+        let id = TransmitId(self.next_id.fetch_add(1, Ordering::Relaxed));
+        let worker = match self.worker() {
+            Ok(worker) => worker,
+            Err(e) => {
+                debug!("transmit_async: failed to spawn the transmit thread: {e}");
+                self.completion_forwarders.forward(&TransmitCompletion {
+                    id,
+                    result: Err(TransmitFailure::new("thread::spawn")
+                        .with_opcode(command.opcode)
+                        .with_address(command.destination)),
+                });
+                return id;
+            }
+        };
+        // Counted before sending, the worker may finish the command before `send` returns
+        self.pending.add();
+        // The worker only stops when the queue is dropped, which cannot happen during `&self`
+        worker.sender.send(Job::Transmit(id, command)).unwrap();
+        id
+    }
+
+    /// `Caller` queueing libcec calls behind the commands of this queue, spawning the worker
+    /// if needed
+    pub(crate) fn caller(&self) -> io::Result<Caller> {
+        Ok(Caller {
+            sender: self.worker()?.sender.clone(),
+            pending: self.pending.clone(),
+        })
+    }
+
+    /// Wait until every queued command has been transmitted and its completion forwarded,
+    /// returning `false` if that takes longer than `timeout`
+    pub(crate) fn flush(&self, timeout: Duration) -> bool {
+        self.pending.wait(timeout)
+    }
+
+    pub(crate) fn completion_receiver(&self) -> mpsc::Receiver<TransmitCompletion> {
+        self.completion_forwarders.subscribe()
+    }
+
+    fn worker(&self) -> io::Result<&Worker> {
+        match self.worker.get() {
+            Some(worker) => Ok(worker),
+            // Another thread may have won the race, its worker is kept and this one exits as
+            // soon as its sender is dropped
+            None => {
+                let worker = self.spawn()?;
+                Ok(self.worker.get_or_init(|| worker))
+            }
+        }
+    }
+
+    fn spawn(&self) -> io::Result<Worker> {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let transmit = self.transmit.clone();
+        let completion_forwarders = self.completion_forwarders.clone();
+        let pending = self.pending.clone();
+        let thread = thread::Builder::new()
+            .name("cec-transmit".into())
+            .spawn(move || {
+                for job in receiver {
+                    match job {
+                        Job::Transmit(id, command) => {
+                            let result = transmit(&command);
+                            completion_forwarders.forward(&TransmitCompletion { id, result });
+                        }
+                        Job::Call(call) => call(),
+                        Job::Stop => break,
+                    }
+                    pending.finish();
+                }
+            })?;
+        Ok(Worker { sender, thread })
+    }
+
+    /// Stop the worker after it transmitted the queued commands, if it was spawned
+    pub(crate) fn shutdown(&mut self) {
+        if let Some(Worker { sender, thread }) = self.worker.take() {
+            // Ends the worker's loop once the queued commands are transmitted. Fails only if
+            // the worker panicked.
+            let _ = sender.send(Job::Stop);
+            // A panic on the worker already lost its commands, nothing left to report
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Caller {
+    /// Run `call` on the worker thread, after the commands already queued
+    ///
+    /// Dropped without running once the queue is shut down.
+    pub(crate) fn call(&self, call: Box<CallFn>) {
+        self.pending.add();
+        if self.sender.send(Job::Call(call)).is_err() {
+            self.pending.finish();
+        }
+    }
+}
+
+impl Drop for TransmitQueue {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+impl Connection {
+    /// Queue `command` for transmission on a background thread and return immediately
+    ///
+    /// Commands queued with `transmit_async` are transmitted one at a time, in the order
+    /// they were queued, and their completions arrive in that same order on
+    /// `transmit_completion_receiver`. There is no ordering between them and `transmit` or
+    /// other blocking calls from other threads, e.g. a command passed to `transmit` after
+    /// `transmit_async` may be sent first. Replies are delivered to `on_command_received` as
+    /// usual.
+    ///
+    /// Dropping or closing the connection blocks until the queued commands are transmitted.
+    /// If the background thread cannot be spawned, the command is not transmitted and its
+    /// completion reports a failed `thread::spawn` call.
+    pub fn transmit_async(&self, command: Cmd) -> TransmitId {
+        self.3.push(command)
+    }
+
+    /// Receive the outcome of every command queued with `transmit_async` through a channel
+    ///
+    /// Only completions after subscribing are received, see `command_receiver`.
+    pub fn transmit_completion_receiver(&self) -> mpsc::Receiver<TransmitCompletion> {
+        self.3.completion_receiver()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::{LogicalAddress, Opcode};
+
+    fn queue(sent: Arc<Mutex<Vec<Cmd>>>) -> TransmitQueue {
+        TransmitQueue::new(Box::new(move |command: &Cmd| {
+            sent.lock().unwrap().push(command.clone());
+            match command.opcode {
+                Opcode::Standby => Err(TransmitFailure::new("libcec_transmit")
+                    .with_opcode(command.opcode)
+                    .with_address(command.destination)),
+                _ => Ok(()),
+            }
+        }))
+    }
+
+    #[test]
+    fn test_order_and_completions() {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let queue = queue(sent.clone());
+        let completions = queue.completion_receiver();
+
+        let first = queue.push(Cmd::image_view_on(
+            LogicalAddress::Playbackdevice1,
+            LogicalAddress::Tv,
+        ));
+        let second = queue.push(Cmd::standby(
+            LogicalAddress::Playbackdevice1,
+            LogicalAddress::Tv,
+        ));
+        assert!(first < second);
+
+        let timeout = Duration::from_secs(5);
+        assert_eq!(
+            completions.recv_timeout(timeout).unwrap(),
+            TransmitCompletion {
+                id: first,
+                result: Ok(())
+            }
+        );
+        let completion = completions.recv_timeout(timeout).unwrap();
+        assert_eq!(completion.id, second);
+        assert_eq!(completion.result.unwrap_err().opcode, Some(Opcode::Standby));
+
+        let opcodes: Vec<_> = sent.lock().unwrap().iter().map(|x| x.opcode).collect();
+        assert_eq!(opcodes, [Opcode::ImageViewOn, Opcode::Standby]);
+    }
+
+    #[test]
+    fn test_drop_transmits_queued() {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let queue = queue(sent.clone());
+        for _ in 0..10 {
+            queue.push(Cmd::image_view_on(
+                LogicalAddress::Playbackdevice1,
+                LogicalAddress::Tv,
+            ));
+        }
+        drop(queue);
+        assert_eq!(sent.lock().unwrap().len(), 10);
+    }
+
+    #[test]
+    fn test_flush() {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let queue = queue(sent.clone());
+        // Nothing queued yet, and no worker to wait for
+        assert!(queue.flush(Duration::ZERO));
+        for _ in 0..10 {
+            queue.push(Cmd::image_view_on(
+                LogicalAddress::Playbackdevice1,
+                LogicalAddress::Tv,
+            ));
+        }
+        assert!(queue.flush(Duration::from_secs(5)));
+        assert_eq!(sent.lock().unwrap().len(), 10);
+    }
+
+    #[test]
+    fn test_flush_timeout() {
+        let queue = TransmitQueue::new(Box::new(|_: &Cmd| {
+            thread::sleep(Duration::from_millis(200));
+            Ok(())
+        }));
+        queue.push(Cmd::image_view_on(
+            LogicalAddress::Playbackdevice1,
+            LogicalAddress::Tv,
+        ));
+        assert!(!queue.flush(Duration::from_millis(1)));
+        assert!(queue.flush(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn test_caller() {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let queue = queue(sent.clone());
+        let caller = queue.caller().unwrap();
+        queue.push(Cmd::image_view_on(
+            LogicalAddress::Playbackdevice1,
+            LogicalAddress::Tv,
+        ));
+        let called = Arc::new(Mutex::new(None));
+        {
+            let (sent, called) = (sent.clone(), called.clone());
+            // Runs after the command queued before it
+            caller.call(Box::new(move || {
+                *called.lock().unwrap() = Some(sent.lock().unwrap().len())
+            }));
+        }
+        assert!(queue.flush(Duration::from_secs(5)));
+        assert_eq!(*called.lock().unwrap(), Some(1));
+
+        // A caller outliving the worker does not keep it running
+        drop(queue);
+        caller.call(Box::new(|| unreachable!()));
+    }
+
+    #[test]
+    fn test_lazy_worker() {
+        let queue = queue(Arc::default());
+        assert!(queue.worker.get().is_none());
+    }
+}
@@ -3,7 +3,7 @@ use std::ffi::c_int;
 use cec_sys::*;
 use enum_repr::EnumRepr;
 
-use crate::TryFromLogicalAddressesError;
+use crate::{TryFromCmdError, TryFromKeypressError, TryFromLogicalAddressesError};
 
 #[EnumRepr(type = "cec_abort_reason")]
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -35,14 +35,23 @@ pub enum AudioRate {
     SlowRateMin999 = cec_audio_rate::SLOW_RATE_MIN_99_9,
 }
 
-#[EnumRepr(type = "cec_audio_status")]
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
-pub enum AudioStatus {
-    MuteStatusMask = cec_audio_status::MUTE_STATUS_MASK,
-    VolumeStatusMask = cec_audio_status::VOLUME_STATUS_MASK,
-    VolumeMin = cec_audio_status::VOLUME_MIN,
-    VolumeMax = cec_audio_status::VOLUME_MAX,
+// In its own module so that the impls generated by `EnumRepr` don't trip the lint
+#[allow(deprecated)]
+mod audio_status {
+    use super::*;
+
+    #[deprecated(note = "these are bit masks of the status byte, use `AudioState` instead")]
+    #[EnumRepr(type = "cec_audio_status")]
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+    pub enum AudioStatus {
+        MuteStatusMask = cec_audio_status::MUTE_STATUS_MASK,
+        VolumeStatusMask = cec_audio_status::VOLUME_STATUS_MASK,
+        VolumeMin = cec_audio_status::VOLUME_MIN,
+        VolumeMax = cec_audio_status::VOLUME_MAX,
+    }
 }
+#[allow(deprecated)]
+pub use audio_status::AudioStatus;
 
 #[EnumRepr(type = "cec_version")]
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -101,6 +110,7 @@ pub enum DeckInfo {
 pub enum DeviceKind {
     Tv = cec_device_type::TV,
     RecordingDevice = cec_device_type::RECORDING_DEVICE,
+    /// Marks the unused slots of libcec's device type list, not a device kind to use
     Reserved = cec_device_type::RESERVED,
     Tuner = cec_device_type::TUNER,
     PlaybackDevice = cec_device_type::PLAYBACK_DEVICE,
@@ -622,6 +632,33 @@ pub enum ParameterType {
     Unknown = libcec_parameter_type::UNKOWN,
 }
 
+const LOGICAL_ADDRESSES: [LogicalAddress; 17] = [
+    LogicalAddress::Unknown,
+    LogicalAddress::Tv,
+    LogicalAddress::Recordingdevice1,
+    LogicalAddress::Recordingdevice2,
+    LogicalAddress::Tuner1,
+    LogicalAddress::Playbackdevice1,
+    LogicalAddress::Audiosystem,
+    LogicalAddress::Tuner2,
+    LogicalAddress::Tuner3,
+    LogicalAddress::Playbackdevice2,
+    LogicalAddress::Recordingdevice3,
+    LogicalAddress::Tuner4,
+    LogicalAddress::Playbackdevice3,
+    LogicalAddress::Reserved1,
+    LogicalAddress::Reserved2,
+    LogicalAddress::Freeuse,
+    LogicalAddress::Unregistered,
+];
+
+impl LogicalAddress {
+    /// Every logical address, ordered by address
+    pub fn all() -> &'static [LogicalAddress] {
+        &LOGICAL_ADDRESSES
+    }
+}
+
 impl TryFrom<c_int> for LogicalAddress {
     type Error = TryFromLogicalAddressesError;
 
@@ -650,3 +687,310 @@ impl TryFrom<c_int> for LogicalAddress {
         Ok(x)
     }
 }
+
+/// Orders by address, which is also the bus priority: TV first, then recording devices,
+/// tuners, playback devices and the audio system interleaved as numbered
+impl Ord for LogicalAddress {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.repr() as c_int).cmp(&(other.repr() as c_int))
+    }
+}
+
+impl PartialOrd for LogicalAddress {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl LogicalAddress {
+    /// Address 15 as a destination, the same value as `Unregistered`
+    ///
+    /// As an initiator 15 means the device has no logical address, as a destination the
+    /// command is broadcast to every device on the bus.
+    pub const BROADCAST: LogicalAddress = LogicalAddress::Unregistered;
+
+    /// Whether this is address 15, i.e. a broadcast when used as a destination
+    pub fn is_broadcast(&self) -> bool {
+        *self == LogicalAddress::BROADCAST
+    }
+
+    pub fn is_tv(&self) -> bool {
+        *self == LogicalAddress::Tv
+    }
+
+    pub fn is_audio_system(&self) -> bool {
+        *self == LogicalAddress::Audiosystem
+    }
+
+    pub fn is_playback(&self) -> bool {
+        matches!(
+            self,
+            LogicalAddress::Playbackdevice1
+                | LogicalAddress::Playbackdevice2
+                | LogicalAddress::Playbackdevice3
+        )
+    }
+
+    pub fn is_recording(&self) -> bool {
+        matches!(
+            self,
+            LogicalAddress::Recordingdevice1
+                | LogicalAddress::Recordingdevice2
+                | LogicalAddress::Recordingdevice3
+        )
+    }
+
+    pub fn is_tuner(&self) -> bool {
+        matches!(
+            self,
+            LogicalAddress::Tuner1
+                | LogicalAddress::Tuner2
+                | LogicalAddress::Tuner3
+                | LogicalAddress::Tuner4
+        )
+    }
+}
+
+const USER_CONTROL_CODES: [UserControlCode; 88] = [
+    UserControlCode::Select,
+    UserControlCode::Up,
+    UserControlCode::Down,
+    UserControlCode::Left,
+    UserControlCode::Right,
+    UserControlCode::RightUp,
+    UserControlCode::RightDown,
+    UserControlCode::LeftUp,
+    UserControlCode::LeftDown,
+    UserControlCode::RootMenu,
+    UserControlCode::SetupMenu,
+    UserControlCode::ContentsMenu,
+    UserControlCode::FavoriteMenu,
+    UserControlCode::Exit,
+    UserControlCode::TopMenu,
+    UserControlCode::DvdMenu,
+    UserControlCode::NumberEntryMode,
+    UserControlCode::Number11,
+    UserControlCode::Number12,
+    UserControlCode::Number0,
+    UserControlCode::Number1,
+    UserControlCode::Number2,
+    UserControlCode::Number3,
+    UserControlCode::Number4,
+    UserControlCode::Number5,
+    UserControlCode::Number6,
+    UserControlCode::Number7,
+    UserControlCode::Number8,
+    UserControlCode::Number9,
+    UserControlCode::Dot,
+    UserControlCode::Enter,
+    UserControlCode::Clear,
+    UserControlCode::NextFavorite,
+    UserControlCode::ChannelUp,
+    UserControlCode::ChannelDown,
+    UserControlCode::PreviousChannel,
+    UserControlCode::SoundSelect,
+    UserControlCode::InputSelect,
+    UserControlCode::DisplayInformation,
+    UserControlCode::Help,
+    UserControlCode::PageUp,
+    UserControlCode::PageDown,
+    UserControlCode::Power,
+    UserControlCode::VolumeUp,
+    UserControlCode::VolumeDown,
+    UserControlCode::Mute,
+    UserControlCode::Play,
+    UserControlCode::Stop,
+    UserControlCode::Pause,
+    UserControlCode::Record,
+    UserControlCode::Rewind,
+    UserControlCode::FastForward,
+    UserControlCode::Eject,
+    UserControlCode::Forward,
+    UserControlCode::Backward,
+    UserControlCode::StopRecord,
+    UserControlCode::PauseRecord,
+    UserControlCode::Angle,
+    UserControlCode::SubPicture,
+    UserControlCode::VideoOnDemand,
+    UserControlCode::ElectronicProgramGuide,
+    UserControlCode::TimerProgramming,
+    UserControlCode::InitialConfiguration,
+    UserControlCode::SelectBroadcastType,
+    UserControlCode::SelectSoundPresentation,
+    UserControlCode::PlayFunction,
+    UserControlCode::PausePlayFunction,
+    UserControlCode::RecordFunction,
+    UserControlCode::PauseRecordFunction,
+    UserControlCode::StopFunction,
+    UserControlCode::MuteFunction,
+    UserControlCode::RestoreVolumeFunction,
+    UserControlCode::TuneFunction,
+    UserControlCode::SelectMediaFunction,
+    UserControlCode::SelectAvInputFunction,
+    UserControlCode::SelectAudioInputFunction,
+    UserControlCode::PowerToggleFunction,
+    UserControlCode::PowerOffFunction,
+    UserControlCode::PowerOnFunction,
+    UserControlCode::F1Blue,
+    UserControlCode::F2Red,
+    UserControlCode::F3Green,
+    UserControlCode::F4Yellow,
+    UserControlCode::F5,
+    UserControlCode::Data,
+    UserControlCode::AnReturn,
+    UserControlCode::AnChannelsList,
+    UserControlCode::Unknown,
+];
+
+impl UserControlCode {
+    /// Every user control code
+    pub fn all() -> &'static [UserControlCode] {
+        &USER_CONTROL_CODES
+    }
+}
+
+impl TryFrom<u8> for UserControlCode {
+    type Error = TryFromKeypressError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        from_u8(value).ok_or(TryFromKeypressError::UnknownKeycode)
+    }
+}
+
+const OPCODES: [Opcode; 72] = [
+    Opcode::ActiveSource,
+    Opcode::ImageViewOn,
+    Opcode::TextViewOn,
+    Opcode::InactiveSource,
+    Opcode::RequestActiveSource,
+    Opcode::RoutingChange,
+    Opcode::RoutingInformation,
+    Opcode::SetStreamPath,
+    Opcode::Standby,
+    Opcode::RecordOff,
+    Opcode::RecordOn,
+    Opcode::RecordStatus,
+    Opcode::RecordTvScreen,
+    Opcode::ClearAnalogueTimer,
+    Opcode::ClearDigitalTimer,
+    Opcode::ClearExternalTimer,
+    Opcode::SetAnalogueTimer,
+    Opcode::SetDigitalTimer,
+    Opcode::SetExternalTimer,
+    Opcode::SetTimerProgramTitle,
+    Opcode::TimerClearedStatus,
+    Opcode::TimerStatus,
+    Opcode::CecVersion,
+    Opcode::GetCecVersion,
+    Opcode::GivePhysicalAddress,
+    Opcode::GetMenuLanguage,
+    Opcode::ReportPhysicalAddress,
+    Opcode::SetMenuLanguage,
+    Opcode::DeckControl,
+    Opcode::DeckStatus,
+    Opcode::GiveDeckStatus,
+    Opcode::Play,
+    Opcode::GiveTunerDeviceStatus,
+    Opcode::SelectAnalogueService,
+    Opcode::SelectDigitalService,
+    Opcode::TunerDeviceStatus,
+    Opcode::TunerStepDecrement,
+    Opcode::TunerStepIncrement,
+    Opcode::DeviceVendorId,
+    Opcode::GiveDeviceVendorId,
+    Opcode::VendorCommand,
+    Opcode::VendorCommandWithId,
+    Opcode::VendorRemoteButtonDown,
+    Opcode::VendorRemoteButtonUp,
+    Opcode::SetOsdString,
+    Opcode::GiveOsdName,
+    Opcode::SetOsdName,
+    Opcode::MenuRequest,
+    Opcode::MenuStatus,
+    Opcode::UserControlPressed,
+    Opcode::UserControlRelease,
+    Opcode::GiveDevicePowerStatus,
+    Opcode::ReportPowerStatus,
+    Opcode::FeatureAbort,
+    Opcode::Abort,
+    Opcode::GiveAudioStatus,
+    Opcode::GiveSystemAudioModeStatus,
+    Opcode::ReportAudioStatus,
+    Opcode::SetSystemAudioMode,
+    Opcode::SystemAudioModeRequest,
+    Opcode::SystemAudioModeStatus,
+    Opcode::SetAudioRate,
+    Opcode::ReportShortAudioDescriptors,
+    Opcode::RequestShortAudioDescriptors,
+    Opcode::StartArc,
+    Opcode::ReportArcStarted,
+    Opcode::ReportArcEnded,
+    Opcode::RequestArcStart,
+    Opcode::RequestArcEnd,
+    Opcode::EndArc,
+    Opcode::Cdc,
+    Opcode::None,
+];
+
+impl Opcode {
+    /// Every opcode, including `None`
+    pub fn all() -> &'static [Opcode] {
+        &OPCODES
+    }
+}
+
+impl TryFrom<u8> for Opcode {
+    type Error = TryFromCmdError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        from_u8(value).ok_or(TryFromCmdError::UnknownOpcode)
+    }
+}
+
+/// Enums carried in a single operand byte, decoded with `from_u8`
+pub(crate) trait U8Variants: Copy + 'static {
+    /// Every variant
+    const VARIANTS: &'static [Self];
+
+    /// The operand byte of `self`
+    fn to_u8(self) -> u8;
+}
+
+/// Decode an operand byte, `None` when no variant of `T` has that value
+pub(crate) fn from_u8<T: U8Variants>(byte: u8) -> Option<T> {
+    T::VARIANTS.iter().copied().find(|x| x.to_u8() == byte)
+}
+
+macro_rules! u8_variants {
+    ($name:ident, $variants:expr) => {
+        impl U8Variants for $name {
+            const VARIANTS: &'static [Self] = $variants;
+
+            fn to_u8(self) -> u8 {
+                self.repr() as u8
+            }
+        }
+    };
+    ($name:ident: $($variant:ident),+ $(,)?) => {
+        u8_variants!($name, &[$($name::$variant),+]);
+    };
+}
+
+u8_variants!(UserControlCode, &USER_CONTROL_CODES);
+u8_variants!(Opcode, &OPCODES);
+u8_variants!(
+    AbortReason:
+        UnrecognizedOpcode, NotInCorrectModeToRespond, CannotProvideSource, InvalidOperand, Refused,
+);
+u8_variants!(AnalogueBroadcastType: Cable, Satellite, Terrestial);
+u8_variants!(
+    BroadcastSystem:
+        PalBG, SecamL1, PalM, NtscM, PalI, SecamDk, SecamBG, SecamL2, PalDk, OtherSystem,
+);
+u8_variants!(DeviceKind: Tv, RecordingDevice, Reserved, Tuner, PlaybackDevice, AudioSystem);
+u8_variants!(PowerStatus: On, Standby, InTransitionStandbyToOn, InTransitionOnToStandby, Unknown);
+u8_variants!(SystemAudioStatus: Off, On);
+u8_variants!(
+    TimerClearedStatusData:
+        NotClearedRecording, NotClearedNoMatching, NotClearedNoInf0Available, Cleared,
+);
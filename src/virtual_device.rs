@@ -0,0 +1,219 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use arrayvec::ArrayVec;
+use log::trace;
+
+use crate::{
+    Cfg, Cmd, Connection, DataPacket, DeviceKind, LogicalAddress, Opcode, PowerStatus, Result,
+    UserControlCode,
+};
+
+/// Device presented on the bus by this process, e.g. a playback device the TV can switch to
+///
+/// libcec claims a logical address for the configured `kind` and answers the standard
+/// queries for it, i.e. physical address, vendor id, OSD name and CEC version. On top of
+/// that, `VirtualCecDevice` tracks the power and active source state of the device and
+/// announces changes made with its setters to the TV.
+///
+/// The state is updated from the bus as well: a `Standby` puts the device in standby, the
+/// `PowerOnFunction` key powers it on, and libcec reports when the device becomes, or stops
+/// being, the active source.
+#[derive(Debug)]
+pub struct VirtualCecDevice {
+    connection: Connection,
+    kind: DeviceKind,
+    state: Arc<Mutex<VirtualDeviceState>>,
+}
+
+/// Power and active source state of a `VirtualCecDevice`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct VirtualDeviceState {
+    pub power_status: PowerStatus,
+    /// `false` until libcec reports this device as the active source.
+    pub active_source: bool,
+}
+
+impl Default for VirtualDeviceState {
+    fn default() -> Self {
+        VirtualDeviceState {
+            power_status: PowerStatus::On,
+            active_source: false,
+        }
+    }
+}
+
+impl VirtualDeviceState {
+    /// Apply a command received from the bus
+    fn on_cmd(&mut self, command: &Cmd) {
+        match command.opcode {
+            Opcode::Standby => {
+                self.power_status = PowerStatus::Standby;
+                self.active_source = false;
+            }
+            Opcode::UserControlPressed
+                if command.parameters.0.first()
+                    == Some(&(UserControlCode::PowerOnFunction.repr() as u8)) =>
+            {
+                self.power_status = PowerStatus::On;
+            }
+            _ => return,
+        }
+        trace!("virtual device state changed: {self:?}");
+    }
+}
+
+impl Cfg {
+    /// Open a connection presenting the configured device on the bus, see `VirtualCecDevice`
+    ///
+    /// The command and source activated callbacks of this configuration are still called,
+    /// after the state of the device has been updated.
+    ///
+    /// # Errors
+    ///
+    /// Same as `Cfg::connect`
+    pub fn connect_virtual(mut self) -> Result<VirtualCecDevice> {
+        let state = Arc::new(Mutex::new(VirtualDeviceState::default()));
+
+        let observed = state.clone();
+        let mut on_command_received = self.on_command_received.take();
+        self.on_command_received = Some(Box::new(move |command: Cmd| {
+            observed.lock().unwrap().on_cmd(&command);
+            if let Some(callback) = &mut on_command_received {
+                callback(command);
+            }
+        }));
+
+        let observed = state.clone();
+        let mut on_source_activated = self.on_source_activated.take();
+        self.on_source_activated = Some(Box::new(move |address, activated| {
+            observed.lock().unwrap().active_source = activated;
+            if let Some(callback) = &mut on_source_activated {
+                callback(address, activated);
+            }
+        }));
+
+        let kind = self.kind;
+        Ok(VirtualCecDevice {
+            connection: self.connect()?,
+            kind,
+            state,
+        })
+    }
+}
+
+impl VirtualCecDevice {
+    /// Connection the device is presented on, for anything not covered by the device
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    pub fn state(&self) -> VirtualDeviceState {
+        *self.state.lock().unwrap()
+    }
+
+    /// Change the power status and report it to the TV with `ReportPowerStatus`
+    ///
+    /// Going to any status other than `On` releases the active source first.
+    pub fn set_power_status(&self, status: PowerStatus) -> Result<()> {
+        if status != PowerStatus::On && self.state().active_source {
+            self.set_active_source(false)?;
+        }
+        self.state.lock().unwrap().power_status = status;
+
+        let initiator = self.connection.get_logical_addresses()?.primary.into();
+        self.connection
+            .transmit(report_power_status(initiator, status))
+    }
+
+    /// Make this device the active source with `libcec_set_active_source`, or release it
+    /// with `libcec_set_inactive_view`
+    pub fn set_active_source(&self, active: bool) -> Result<()> {
+        if active {
+            self.connection.set_active_source(self.kind)?;
+        } else {
+            self.connection.set_inactive_view()?;
+        }
+        self.state.lock().unwrap().active_source = active;
+        Ok(())
+    }
+}
+
+/// `ReportPowerStatus` from `initiator` to the TV
+fn report_power_status(initiator: LogicalAddress, status: PowerStatus) -> Cmd {
+    let mut parameters = ArrayVec::new();
+    parameters.push(status.repr() as u8);
+    Cmd {
+        initiator,
+        destination: LogicalAddress::Tv,
+        ack: false,
+        eom: true,
+        opcode: Opcode::ReportPowerStatus,
+        parameters: DataPacket(parameters),
+        opcode_set: true,
+        transmit_timeout: Duration::from_millis(1000),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cmd(opcode: Opcode, parameters: &[u8]) -> Cmd {
+        Cmd {
+            parameters: DataPacket(ArrayVec::try_from(parameters).unwrap()),
+            opcode,
+            ..report_power_status(LogicalAddress::Tv, PowerStatus::On)
+        }
+    }
+
+    #[test]
+    fn test_standby() {
+        let mut state = VirtualDeviceState {
+            power_status: PowerStatus::On,
+            active_source: true,
+        };
+        state.on_cmd(&cmd(Opcode::Standby, &[]));
+        assert_eq!(
+            state,
+            VirtualDeviceState {
+                power_status: PowerStatus::Standby,
+                active_source: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_power_on_function() {
+        let mut state = VirtualDeviceState {
+            power_status: PowerStatus::Standby,
+            active_source: false,
+        };
+        let power_on = UserControlCode::PowerOnFunction.repr() as u8;
+        state.on_cmd(&cmd(Opcode::UserControlPressed, &[power_on]));
+        assert_eq!(state.power_status, PowerStatus::On);
+    }
+
+    #[test]
+    fn test_other_commands_ignored() {
+        let mut state = VirtualDeviceState::default();
+        let volume_up = UserControlCode::VolumeUp.repr() as u8;
+        state.on_cmd(&cmd(Opcode::UserControlPressed, &[volume_up]));
+        state.on_cmd(&cmd(Opcode::GiveDevicePowerStatus, &[]));
+        assert_eq!(state, VirtualDeviceState::default());
+    }
+
+    #[test]
+    fn test_report_power_status() {
+        let command = report_power_status(LogicalAddress::Playbackdevice1, PowerStatus::Standby);
+        assert_eq!(command.initiator, LogicalAddress::Playbackdevice1);
+        assert_eq!(command.destination, LogicalAddress::Tv);
+        assert_eq!(command.opcode, Opcode::ReportPowerStatus);
+        assert_eq!(
+            command.parameters.0.as_slice(),
+            &[PowerStatus::Standby.repr() as u8]
+        );
+    }
+}
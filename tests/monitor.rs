@@ -0,0 +1,19 @@
+//! Needs a CEC adapter, run with `cargo test --features hardware-tests`
+#![cfg(all(feature = "hardware-tests", feature = "builder"))]
+
+use cec::{CfgBuilder, Cmd, LogicalAddress};
+
+#[test]
+fn test_monitor_preset() {
+    let connection = CfgBuilder::monitor_preset("monitor".into())
+        .detect_device(true)
+        .connect()
+        .unwrap();
+    // No client is allocated, so nothing can be sent
+    assert!(connection
+        .transmit(Cmd::image_view_on(
+            LogicalAddress::Playbackdevice1,
+            LogicalAddress::Tv
+        ))
+        .is_err());
+}